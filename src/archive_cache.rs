@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use adw::glib;
+
+use crate::storage::{cache_dir, load_json, save_json};
+use crate::{Catalog, DownloadDetail, Product, Result};
+
+const PART_EXTENSION: &str = "part";
+const ENTRY_EXTENSION: &str = "json";
+// Leftovers younger than this may still belong to a running download
+const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+// Written next to an archive once it was downloaded completely and checked, an archive
+// without one is never reused
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedArchive {
+    pub productid: i64,
+    pub name: String,
+    pub catalog: Catalog,
+    pub downloadname: String,
+    pub changed: String,
+    #[serde(default)]
+    pub md5sum: String,
+    pub size: u64,
+    pub downloaded: String,
+    // Filled in when listing, not stored
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Default for CachedArchive {
+    fn default() -> CachedArchive {
+        CachedArchive {
+            productid: 0,
+            name: String::new(),
+            catalog: Catalog::FullIconThemes,
+            downloadname: String::new(),
+            changed: String::new(),
+            md5sum: String::new(),
+            size: 0,
+            downloaded: String::new(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl CachedArchive {
+    fn entry_path(&self) -> PathBuf {
+        entry_path(&self.path)
+    }
+
+    // Removes the archive, its entry and the product directory once it is empty
+    pub fn delete(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        if self.entry_path().exists() {
+            fs::remove_file(self.entry_path())?;
+        }
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+        Ok(())
+    }
+}
+
+// ~/.cache/linuxthemestore/archives
+pub fn archive_cache_dir() -> PathBuf {
+    cache_dir().join("archives")
+}
+
+// One directory per product and store revision, so an updated product with the same
+// file names never reuses the archives of the old revision
+pub fn archive_path(product: &Product, downloaddetail: &DownloadDetail) -> PathBuf {
+    let revision: String = product
+        .changed
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    archive_cache_dir()
+        .join(format!("{}-{}", product.id, revision))
+        .join(file_name(&downloaddetail.downloadname))
+}
+
+fn file_name(downloadname: &str) -> String {
+    match Path::new(downloadname).file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => String::from("archive"),
+    }
+}
+
+fn entry_path(archive: &Path) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(".");
+    path.push(ENTRY_EXTENSION);
+    PathBuf::from(path)
+}
+
+// Unique per download, so two jobs fetching the same archive never write into one file
+fn part_path(archive: &Path) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(format!(
+        ".{}-{}.{}",
+        std::process::id(),
+        glib::monotonic_time(),
+        PART_EXTENSION
+    ));
+    PathBuf::from(path)
+}
+
+fn download(url: &str, path: &Path) -> Result<()> {
+    let mut response = reqwest::blocking::get(url)?.error_for_status()?;
+    let mut file = fs::File::create(path)?;
+    std::io::copy(&mut response, &mut file)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn md5sum(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut checksum =
+        glib::Checksum::new(glib::ChecksumType::Md5).ok_or("MD5 checksums are not available")?;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        checksum.update(&buffer[..read]);
+    }
+    Ok(checksum.string().unwrap_or_default().to_string())
+}
+
+// Checks a downloaded file against what the store reports. Without a checksum only the
+// size is left, which the store rounds to kilobytes.
+fn verify(path: &Path, downloaddetail: &DownloadDetail) -> Result<(u64, String)> {
+    let size = fs::metadata(path)?.len();
+    if size == 0 {
+        return Err(format!("Download of {} is empty", downloaddetail.downloadname).into());
+    }
+    let expected_size = downloaddetail.size_in_bytes();
+    if downloaddetail.downloadmd5sum.is_empty()
+        && expected_size > 0
+        && size.abs_diff(expected_size) >= 1024
+    {
+        return Err(format!(
+            "Download of {} is incomplete : {} of {} bytes",
+            downloaddetail.downloadname, size, expected_size
+        )
+        .into());
+    }
+    let md5 = md5sum(path)?;
+    if !downloaddetail.downloadmd5sum.is_empty()
+        && !md5.eq_ignore_ascii_case(&downloaddetail.downloadmd5sum)
+    {
+        return Err(format!(
+            "Download of {} is damaged : md5 {} but the store has {}",
+            downloaddetail.downloadname, md5, downloaddetail.downloadmd5sum
+        )
+        .into());
+    }
+    Ok((size, md5))
+}
+
+// The cached archive when it is complete and still matches the store, checked again
+// on every use since the cache lives where anything may change it
+pub fn cached_archive(product: &Product, downloaddetail: &DownloadDetail) -> Option<PathBuf> {
+    let path = archive_path(product, downloaddetail);
+    let entry_path = entry_path(&path);
+    if !path.is_file() || !entry_path.is_file() {
+        return None;
+    }
+    let entry: CachedArchive = load_json(&entry_path);
+    let size = fs::metadata(&path).ok()?.len();
+    if entry.size != size {
+        return None;
+    }
+    match verify(&path, downloaddetail) {
+        Ok(_) => Some(path),
+        Err(e) => {
+            eprintln!("Not reusing {} : {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// The cached archive, downloading it first when there is no usable copy
+pub fn fetch_archive(
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+    catalog: &Catalog,
+) -> Result<PathBuf> {
+    match cached_archive(product, downloaddetail) {
+        Some(path) => Ok(path),
+        None => download_to_cache(product, downloaddetail, catalog),
+    }
+}
+
+// Downloads into a .part file and only moves it into the cache once it checked out,
+// so an interrupted download is never mistaken for a complete archive
+pub fn download_to_cache(
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+    catalog: &Catalog,
+) -> Result<PathBuf> {
+    let path = archive_path(product, downloaddetail);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let part = part_path(&path);
+    let checked = download(&downloaddetail.downloadlink, &part)
+        .and_then(|()| verify(&part, downloaddetail))
+        .and_then(|checked| {
+            fs::rename(&part, &path)?;
+            Ok(checked)
+        });
+    let (size, md5sum) = match checked {
+        Ok(checked) => checked,
+        Err(e) => {
+            let _ = fs::remove_file(&part);
+            return Err(e);
+        }
+    };
+    let entry = CachedArchive {
+        productid: product.id,
+        name: product.name.clone(),
+        catalog: catalog.clone(),
+        downloadname: downloaddetail.downloadname.clone(),
+        changed: product.changed.clone(),
+        md5sum,
+        size,
+        downloaded: chrono::Local::now().to_rfc3339(),
+        path: path.clone(),
+    };
+    save_json(&entry_path(&path), &entry)?;
+    Ok(path)
+}
+
+// Every complete archive in the cache, most recently downloaded first
+pub fn list_cached_archives() -> Vec<CachedArchive> {
+    let mut archives = vec![];
+    let Ok(dirs) = fs::read_dir(archive_cache_dir()) else {
+        return archives;
+    };
+    for dir in dirs.flatten() {
+        let Ok(files) = fs::read_dir(dir.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let entry_path = file.path();
+            if entry_path.extension().is_none_or(|e| e != ENTRY_EXTENSION) {
+                continue;
+            }
+            let archive = entry_path.with_extension("");
+            if !archive.is_file() {
+                continue;
+            }
+            let mut entry: CachedArchive = load_json(&entry_path);
+            if entry.downloadname.is_empty() {
+                continue;
+            }
+            entry.path = archive;
+            archives.push(entry);
+        }
+    }
+    archives.sort_by(|a, b| b.downloaded.cmp(&a.downloaded));
+    archives
+}
+
+// Keeps the cache under `limit_mb` by dropping the oldest downloads, each archive together
+// with its entry. Stale .part files and archives or entries missing their other half go
+// first. A limit of 0 keeps every complete archive.
+pub fn trim_archive_cache(limit_mb: u32) -> Result<()> {
+    let Ok(dirs) = fs::read_dir(archive_cache_dir()) else {
+        return Ok(());
+    };
+    for dir in dirs.flatten() {
+        let Ok(files) = fs::read_dir(dir.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            let stale = match path.extension() {
+                Some(extension) if extension == ENTRY_EXTENSION => {
+                    !path.with_extension("").is_file()
+                }
+                Some(extension) if extension == PART_EXTENSION => is_stale(&path),
+                _ => !entry_path(&path).is_file() && is_stale(&path),
+            };
+            if stale && let Err(e) = fs::remove_file(&path) {
+                eprintln!("Failed to remove {} : {}", path.display(), e);
+            }
+        }
+        // Only succeeds once the product directory is empty
+        let _ = fs::remove_dir(dir.path());
+    }
+    if limit_mb == 0 {
+        return Ok(());
+    }
+    let limit = u64::from(limit_mb) * 1024 * 1024;
+    let archives = list_cached_archives();
+    let mut total: u64 = archives.iter().map(|archive| archive.size).sum();
+    for archive in archives.iter().rev() {
+        if total <= limit {
+            break;
+        }
+        archive.delete()?;
+        total = total.saturating_sub(archive.size);
+    }
+    Ok(())
+}
+
+fn is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > STALE_AFTER)
+}
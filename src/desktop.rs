@@ -0,0 +1,205 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use adw::gio;
+use adw::gio::prelude::SettingsExt;
+use adw::glib;
+
+use crate::index_theme::IndexTheme;
+use crate::installer::InstallScope;
+use crate::manifest::InstallRecord;
+use crate::{Catalog, Result};
+
+const INTERFACE_SCHEMA: &str = "org.gnome.desktop.interface";
+const USER_THEME_SCHEMA: &str = "org.gnome.shell.extensions.user-theme";
+const USER_THEME_EXTENSION: &str = "user-theme@gnome-shell-extensions.gcr.io";
+
+// Only returns settings whose schema is installed, gio aborts on unknown schemas
+pub fn lookup_settings(schema_id: &str) -> Option<gio::Settings> {
+    gio::SettingsSchemaSource::default()?.lookup(schema_id, true)?;
+    Some(gio::Settings::new(schema_id))
+}
+
+// The User Themes extension usually ships its schema inside the extension directory
+fn lookup_user_theme_settings() -> Option<gio::Settings> {
+    if let Some(settings) = lookup_settings(USER_THEME_SCHEMA) {
+        return Some(settings);
+    }
+    let default_source = gio::SettingsSchemaSource::default();
+    let extension_dirs = [
+        glib::user_data_dir().join("gnome-shell/extensions"),
+        PathBuf::from("/usr/share/gnome-shell/extensions"),
+    ];
+    for extension_dir in extension_dirs {
+        let schema_dir = extension_dir.join(USER_THEME_EXTENSION).join("schemas");
+        let source = match gio::SettingsSchemaSource::from_directory(
+            &schema_dir,
+            default_source.as_ref(),
+            false,
+        ) {
+            Ok(source) => source,
+            Err(_) => continue,
+        };
+        if let Some(schema) = source.lookup(USER_THEME_SCHEMA, false) {
+            return Some(gio::Settings::new_full(
+                &schema,
+                None::<&gio::SettingsBackend>,
+                None,
+            ));
+        }
+    }
+    None
+}
+
+// Settings object and key that select the active theme of a catalog
+fn theme_settings(catalog: &Catalog) -> Option<(gio::Settings, &'static str)> {
+    match catalog {
+        Catalog::FullIconThemes => Some((lookup_settings(INTERFACE_SCHEMA)?, "icon-theme")),
+        Catalog::Cursors => Some((lookup_settings(INTERFACE_SCHEMA)?, "cursor-theme")),
+        Catalog::Gtk4Themes => Some((lookup_settings(INTERFACE_SCHEMA)?, "gtk-theme")),
+        Catalog::GnomeShellThemes => Some((lookup_user_theme_settings()?, "name")),
+        Catalog::KDEThemes => None,
+    }
+}
+
+// Name of the theme the desktop currently uses for this catalog
+pub fn current_theme(catalog: &Catalog) -> Option<String> {
+    let name = match catalog {
+        Catalog::KDEThemes => {
+            let plasmarc = fs::read_to_string(glib::user_config_dir().join("plasmarc")).ok()?;
+            IndexTheme::parse(&plasmarc).get("Theme", "name")?
+        }
+        _ => {
+            let (settings, key) = theme_settings(catalog)?;
+            settings.string(key).to_string()
+        }
+    };
+    match name.is_empty() {
+        true => None,
+        false => Some(name),
+    }
+}
+
+// Whether the desktop asks for dark styles. Without the GNOME key this follows
+// the style manager, which reads the portal setting; main thread only.
+pub fn prefers_dark() -> bool {
+    // GNOME before 42 has the schema without the key, and gio aborts on unknown keys
+    match lookup_settings(INTERFACE_SCHEMA).filter(|settings| {
+        settings
+            .settings_schema()
+            .is_some_and(|schema| schema.has_key("color-scheme"))
+    }) {
+        Some(settings) => settings.string("color-scheme") == "prefer-dark",
+        None => adw::StyleManager::default().is_dark(),
+    }
+}
+
+// Switches the desktop to an installed theme, main thread only
+pub fn apply_theme(catalog: &Catalog, name: &str) -> Result<()> {
+    match catalog {
+        Catalog::KDEThemes => {
+            let tool = match glib::find_program_in_path("plasma-apply-desktoptheme") {
+                Some(tool) => tool,
+                None => return Err("plasma-apply-desktoptheme is not available".into()),
+            };
+            let output = Command::new(tool).arg(name).output()?;
+            if !output.status.success() {
+                return Err(format!(
+                    "Failed to apply {} : {}",
+                    name,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )
+                .into());
+            }
+        }
+        _ => match theme_settings(catalog) {
+            Some((settings, key)) => settings.set_string(key, name)?,
+            None => {
+                return Err(
+                    format!("{} can not be applied on this desktop", catalog.to_string()).into(),
+                );
+            }
+        },
+    }
+    Ok(())
+}
+
+// gtk-update-icon-cache, or its GTK 4 name, when one is installed
+pub fn icon_cache_tool() -> Option<PathBuf> {
+    ["gtk4-update-icon-cache", "gtk-update-icon-cache"]
+        .iter()
+        .find_map(glib::find_program_in_path)
+}
+
+// $0 the cache tool or "remove", then the theme directories. Keeps going after a
+// failing directory and reports it on stderr.
+const ICON_CACHE_SCRIPT: &str = r#"status=0
+for dir in "$@"; do
+    if [ "$0" = remove ]; then
+        rm -f -- "$dir/icon-theme.cache" || status=1
+    elif ! "$0" -f -t -q "$dir"; then
+        echo "Failed to update icon cache for $dir" >&2
+        status=1
+    fi
+done
+exit $status"#;
+
+// Regenerates icon-theme.cache for freshly extracted icon or cursor themes.
+// Without gtk-update-icon-cache a stale cache is removed instead, so GTK falls
+// back to scanning the theme directories rather than serving outdated icons.
+// System themes take a single pkexec prompt however many directories there are.
+pub fn refresh_icon_cache(theme_dirs: &[PathBuf], scope: &InstallScope) -> Result<()> {
+    if theme_dirs.is_empty() {
+        return Ok(());
+    }
+    // -t: cursor themes and many icon packs ship without a usable index.theme
+    let tool = icon_cache_tool()
+        .map(|tool| tool.display().to_string())
+        .unwrap_or_else(|| String::from("remove"));
+    let mut command = match scope.is_elevated() {
+        true => {
+            let mut command = Command::new("pkexec");
+            command.arg("sh");
+            command
+        }
+        false => Command::new("sh"),
+    };
+    let output = command
+        .arg("-c")
+        .arg(ICON_CACHE_SCRIPT)
+        .arg(&tool)
+        .args(theme_dirs)
+        .output()?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+    Ok(())
+}
+
+// Running GTK sessions only reread an icon or cursor theme when the setting
+// changes, so briefly switch away from the theme and back again
+pub fn reload_if_active(record: &InstallRecord) {
+    let (key, fallback) = match record.catalog {
+        Catalog::FullIconThemes => ("icon-theme", "hicolor"),
+        Catalog::Cursors => ("cursor-theme", "Adwaita"),
+        _ => return,
+    };
+    let settings = match lookup_settings(INTERFACE_SCHEMA) {
+        Some(settings) => settings,
+        None => return,
+    };
+    let active = settings.string(key).to_string();
+    if !record.directories.contains(&active) || active == fallback {
+        return;
+    }
+    if settings.set_string(key, fallback).is_err() {
+        return;
+    }
+    glib::timeout_add_local_once(std::time::Duration::from_millis(250), move || {
+        let _ = settings.set_string(key, &active);
+    });
+}
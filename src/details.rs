@@ -0,0 +1,206 @@
+use std::fs;
+use std::path::PathBuf;
+
+use reqwest::blocking::Client;
+
+use crate::settings::Settings;
+use crate::{get_product, Product, ProductCatalog, Result, SortType};
+
+// OCS comment type for store content, as opposed to forum or wiki comments
+const CONTENT_COMMENT_TYPE: u8 = 1;
+const COMMENTS_PAGE_SIZE: u8 = 20;
+const OTHER_WORKS_PAGE_SIZE: u8 = 6;
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub subject: String,
+    pub text: String,
+    pub user: String,
+    pub date: String,
+    pub children: Vec<Comment>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Person {
+    pub personid: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub avatarpic: String,
+    pub homepage: String,
+    pub description: String,
+}
+
+impl Comment {
+    fn from_value(value: &serde_json::Value) -> Comment {
+        Comment {
+            subject: value_string(value, "subject"),
+            text: value_string(value, "text"),
+            user: value_string(value, "user"),
+            date: value_string(value, "date"),
+            children: match value.get("children") {
+                Some(serde_json::Value::Array(children)) => {
+                    children.iter().map(Comment::from_value).collect()
+                }
+                _ => vec![],
+            },
+        }
+    }
+}
+
+impl Person {
+    fn from_value(value: &serde_json::Value) -> Person {
+        Person {
+            personid: value_string(value, "personid"),
+            firstname: value_string(value, "firstname"),
+            lastname: value_string(value, "lastname"),
+            avatarpic: value_string(value, "avatarpic"),
+            homepage: value_string(value, "homepage"),
+            description: value_string(value, "description"),
+        }
+    }
+
+    // Real name when the profile has one, the user name otherwise
+    pub fn display_name(&self) -> String {
+        let name = format!("{} {}", self.firstname, self.lastname);
+        match name.trim().is_empty() {
+            true => self.personid.clone(),
+            false => name.trim().to_string(),
+        }
+    }
+}
+
+// Everything the detail dialog loads on top of the listing data. Each part fails on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductPage {
+    pub product: std::result::Result<Product, String>,
+    pub comments: std::result::Result<Vec<Comment>, String>,
+    pub author: std::result::Result<Person, String>,
+    // Local copy of the author's avatar
+    pub avatar: Option<PathBuf>,
+    pub other_works: Vec<Product>,
+}
+
+// Blocking, loads the full content details, comments and author profile
+pub fn fetch_product_page(product: &Product) -> ProductPage {
+    let author = get_person(&product.personid).map_err(|e| e.to_string());
+    let avatar = author
+        .as_ref()
+        .ok()
+        .and_then(|person| download_avatar(person).ok());
+    let other_works = get_person_products(
+        &product.personid,
+        &SortType::Downloads,
+        OTHER_WORKS_PAGE_SIZE,
+    )
+    .unwrap_or_default()
+    .into_iter()
+    .filter(|p| p.id != product.id)
+    .collect();
+    ProductPage {
+        product: get_product(product.id).map_err(|e| e.to_string()),
+        comments: get_comments(product.id).map_err(|e| e.to_string()),
+        author,
+        avatar,
+        other_works,
+    }
+}
+
+fn base_url() -> String {
+    String::from("https://") + Settings::load().provider.get_base_url() + "/ocs/v1"
+}
+
+// OCS fields may be strings, numbers or null
+fn value_string(value: &serde_json::Value, key: &str) -> String {
+    match value.get(key) {
+        Some(serde_json::Value::String(value)) => value.clone(),
+        Some(serde_json::Value::Number(value)) => value.to_string(),
+        _ => String::new(),
+    }
+}
+
+// OCS returns a list for most calls but a bare object for some person lookups
+fn first_item(data: serde_json::Value) -> Option<serde_json::Value> {
+    match data {
+        serde_json::Value::Array(items) => items.into_iter().next(),
+        serde_json::Value::Object(_) => Some(data),
+        _ => None,
+    }
+}
+
+pub fn get_comments(productid: i64) -> Result<Vec<Comment>> {
+    let url = format!(
+        "{}/comments/data/{}/{}/0",
+        base_url(),
+        CONTENT_COMMENT_TYPE,
+        productid
+    );
+    let url = reqwest::Url::parse_with_params(
+        &url,
+        &[
+            ("format", "json"),
+            ("page", "0"),
+            ("pagesize", COMMENTS_PAGE_SIZE.to_string().as_str()),
+        ],
+    )?;
+    let res: serde_json::Value = Client::new().get(url).send()?.json()?;
+    match res.get("data") {
+        Some(serde_json::Value::Array(comments)) => {
+            Ok(comments.iter().map(Comment::from_value).collect())
+        }
+        _ => Ok(vec![]),
+    }
+}
+
+pub fn get_person(personid: &str) -> Result<Person> {
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}/person/data/{}", base_url(), personid),
+        &[("format", "json")],
+    )?;
+    let res: serde_json::Value = Client::new().get(url).send()?.json()?;
+    match res.get("data").cloned().and_then(first_item) {
+        Some(person) => Ok(Person::from_value(&person)),
+        None => Err(format!("User {} not found", personid).into()),
+    }
+}
+
+// First page of one user's products across all catalogs
+pub fn get_person_products(
+    personid: &str,
+    sortby: &SortType,
+    pagesize: u8,
+) -> Result<Vec<Product>> {
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}/content/data", base_url()),
+        &[
+            ("format", "json"),
+            ("user", personid),
+            ("sortmode", sortby.get_label()),
+            ("pagesize", pagesize.to_string().as_str()),
+        ],
+    )?;
+    let res: serde_json::Value = Client::new().get(url).send()?.json()?;
+    let productcatalog: ProductCatalog = serde_json::from_value(res)?;
+    Ok(productcatalog
+        .data
+        .into_iter()
+        .filter(|p| p.personid == personid)
+        .collect())
+}
+
+fn download_avatar(person: &Person) -> Result<PathBuf> {
+    if person.avatarpic.is_empty() {
+        return Err(format!("{} has no avatar", person.personid).into());
+    }
+    let path =
+        PathBuf::from("/tmp/themeinstaller/cache/avatars").join(person.personid.replace('/', "_"));
+    if !path.exists() {
+        let content = Client::new()
+            .get(&person.avatarpic)
+            .send()?
+            .error_for_status()?
+            .bytes()?;
+        fs::create_dir_all(path.parent().unwrap_or(&path))?;
+        fs::write(&path, &content)?;
+    }
+    Ok(path)
+}
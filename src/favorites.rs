@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::storage::{data_dir, load_json, save_json};
+use crate::{Product, Result};
+
+// Enough to list a bookmarked product, everything else is refreshed from the API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Favorite {
+    pub productid: i64,
+    pub name: String,
+    pub typeid: i64,
+    pub personid: String,
+    pub added: String,
+}
+
+// Also the export format, so an exported list can be imported as is
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Favorites {
+    pub favorites: Vec<Favorite>,
+}
+
+impl Favorites {
+    fn path() -> PathBuf {
+        data_dir().join("favorites.json")
+    }
+    pub fn load() -> Favorites {
+        load_json(&Favorites::path())
+    }
+    pub fn save(&self) -> Result<()> {
+        save_json(&Favorites::path(), self)
+    }
+
+    pub fn contains(&self, productid: i64) -> bool {
+        self.favorites.iter().any(|f| f.productid == productid)
+    }
+
+    pub fn set_favorite(&mut self, product: &Product, favorite: bool) {
+        self.favorites.retain(|f| f.productid != product.id);
+        if favorite {
+            self.favorites.push(Favorite {
+                productid: product.id,
+                name: product.name.clone(),
+                typeid: product.typeid,
+                personid: product.personid.clone(),
+                added: chrono::Local::now().to_rfc3339(),
+            });
+        }
+    }
+
+    pub fn export(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Merges a shared list into ours, returns how many products were new
+    pub fn import(&mut self, path: &Path) -> Result<usize> {
+        let imported: Favorites = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let mut added = 0;
+        for favorite in imported.favorites {
+            if !self.contains(favorite.productid) {
+                self.favorites.push(favorite);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+}
+
+// Stores the heart state of one product, called from cards and the detail dialog
+pub fn set_favorite(product: &Product, favorite: bool) {
+    let mut favorites = Favorites::load();
+    if favorites.contains(product.id) == favorite {
+        return;
+    }
+    favorites.set_favorite(product, favorite);
+    if let Err(e) = favorites.save() {
+        eprintln!("Failed to save favorites : {}", e);
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use adw::gtk::prelude::{FlowBoxChildExt, WidgetExt};
+use adw::gtk::{FlowBox, FlowBoxChild};
+use chrono::{DateTime, Months};
+
+use crate::storage::{config_dir, load_json, save_json};
+use crate::{Catalog, Product, Result};
+
+// Client side filter for product grids, zero values mean "any"
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListingFilter {
+    // Same 0 to 10 scale as Product::score
+    pub min_score: f32,
+    pub min_downloads: u64,
+    pub updated_within_months: u32,
+    // At least one .tar.xz or .zip download
+    pub archive_variant: bool,
+    pub has_previews: bool,
+}
+
+impl ListingFilter {
+    fn path() -> PathBuf {
+        config_dir().join("filters.json")
+    }
+    pub fn load(catalog: &Catalog) -> ListingFilter {
+        let filters: HashMap<String, ListingFilter> = load_json(&ListingFilter::path());
+        filters.get(catalog.get_id()).cloned().unwrap_or_default()
+    }
+    pub fn save(&self, catalog: &Catalog) -> Result<()> {
+        let mut filters: HashMap<String, ListingFilter> = load_json(&ListingFilter::path());
+        filters.insert(catalog.get_id().to_string(), self.clone());
+        save_json(&ListingFilter::path(), &filters)
+    }
+
+    pub fn is_active(&self) -> bool {
+        *self != ListingFilter::default()
+    }
+
+    pub fn matches(&self, product: &Product) -> bool {
+        if product.score < self.min_score {
+            return false;
+        }
+        if self.min_downloads > 0
+            && product.downloads.parse::<u64>().unwrap_or(0) < self.min_downloads
+        {
+            return false;
+        }
+        if self.updated_within_months > 0 {
+            let cutoff = chrono::Local::now()
+                .checked_sub_months(Months::new(self.updated_within_months))
+                .unwrap_or_default();
+            match DateTime::parse_from_rfc3339(&product.changed) {
+                Ok(changed) if changed >= cutoff => {}
+                _ => return false,
+            }
+        }
+        if self.archive_variant
+            && !product
+                .downloaddetails
+                .iter()
+                .any(|d| d.downloadname.ends_with(".tar.xz") || d.downloadname.ends_with(".zip"))
+        {
+            return false;
+        }
+        if self.has_previews && product.previewpics.is_empty() {
+            return false;
+        }
+        true
+    }
+}
+
+// Filter shared by every sort tab of one catalog page. Cards are looked up by the
+// product id build_flowbox_for_page stores as the widget name of each grid item.
+#[derive(Clone)]
+pub struct FilteredListing {
+    pub catalog: Catalog,
+    filter: Rc<RefCell<ListingFilter>>,
+    products: Rc<RefCell<HashMap<String, Product>>>,
+    flowboxes: Rc<RefCell<Vec<FlowBox>>>,
+}
+
+impl FilteredListing {
+    pub fn new(catalog: &Catalog) -> FilteredListing {
+        FilteredListing {
+            catalog: catalog.clone(),
+            filter: Rc::new(RefCell::new(ListingFilter::load(catalog))),
+            products: Rc::new(RefCell::new(HashMap::new())),
+            flowboxes: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    // For grids that span catalogs, like an author page, nothing is hidden
+    pub fn unfiltered(catalog: &Catalog) -> FilteredListing {
+        let filteredlisting = FilteredListing::new(catalog);
+        *filteredlisting.filter.borrow_mut() = ListingFilter::default();
+        filteredlisting
+    }
+
+    pub fn filter(&self) -> ListingFilter {
+        self.filter.borrow().clone()
+    }
+
+    pub fn attach(&self, flowbox: &FlowBox) {
+        let filter = self.filter.clone();
+        let products = self.products.clone();
+        flowbox.set_filter_func(move |child: &FlowBoxChild| {
+            let productid = match child.child() {
+                Some(card) => card.widget_name().to_string(),
+                None => return true,
+            };
+            match products.borrow().get(&productid) {
+                Some(product) => filter.borrow().matches(product),
+                None => true,
+            }
+        });
+        self.flowboxes.borrow_mut().push(flowbox.clone());
+    }
+
+    pub fn add_product(&self, product: &Product) {
+        self.products
+            .borrow_mut()
+            .insert(product.id.to_string(), product.clone());
+    }
+
+    // Loaded products that pass the current filter, and all loaded products
+    pub fn counts(&self) -> (usize, usize) {
+        let filter = self.filter.borrow();
+        let products = self.products.borrow();
+        let matching = products.values().filter(|p| filter.matches(p)).count();
+        (matching, products.len())
+    }
+
+    pub fn set_filter(&self, filter: ListingFilter) {
+        if let Err(e) = filter.save(&self.catalog) {
+            eprintln!("Failed to save filters : {}", e);
+        }
+        *self.filter.borrow_mut() = filter;
+        for flowbox in self.flowboxes.borrow().iter() {
+            flowbox.invalidate_filter();
+        }
+    }
+}
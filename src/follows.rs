@@ -0,0 +1,113 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::details::get_person_products;
+use crate::storage::{data_dir, load_json, save_json};
+use crate::{Product, Result, SortType};
+
+// Newest uploads looked at per author and check, more than anyone publishes between two runs
+const NEW_UPLOADS_PAGE_SIZE: u8 = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Follow {
+    pub personid: String,
+    pub followed: String,
+    // Uploads created after this are new, starts at the follow time
+    pub last_checked: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Follows {
+    pub follows: Vec<Follow>,
+}
+
+impl Follows {
+    fn path() -> PathBuf {
+        data_dir().join("follows.json")
+    }
+    pub fn load() -> Follows {
+        load_json(&Follows::path())
+    }
+    pub fn save(&self) -> Result<()> {
+        save_json(&Follows::path(), self)
+    }
+
+    pub fn contains(&self, personid: &str) -> bool {
+        self.follows.iter().any(|f| f.personid == personid)
+    }
+
+    pub fn set_following(&mut self, personid: &str, following: bool) {
+        self.follows.retain(|f| f.personid != personid);
+        if following {
+            let now = chrono::Local::now().to_rfc3339();
+            self.follows.push(Follow {
+                personid: personid.to_string(),
+                followed: now.clone(),
+                last_checked: now,
+            });
+        }
+    }
+}
+
+// Stores the follow state of one author, called from the author page
+pub fn set_following(personid: &str, following: bool) {
+    let mut follows = Follows::load();
+    if follows.contains(personid) == following {
+        return;
+    }
+    follows.set_following(personid, following);
+    if let Err(e) = follows.save() {
+        eprintln!("Failed to save followed authors : {}", e);
+    }
+}
+
+// Blocking, products each followed author created since the last check.
+// Authors that could not be reached keep their old check time and are retried next run.
+pub fn check_new_uploads() -> Vec<(String, Vec<Product>)> {
+    let mut follows = Follows::load();
+    let mut new_uploads = vec![];
+    for follow in follows.follows.iter_mut() {
+        let products = match get_person_products(
+            &follow.personid,
+            &SortType::Creator,
+            NEW_UPLOADS_PAGE_SIZE,
+        ) {
+            Ok(products) => products,
+            Err(e) => {
+                eprintln!("Failed to check uploads of {} : {}", follow.personid, e);
+                continue;
+            }
+        };
+        let last_checked = DateTime::parse_from_rfc3339(&follow.last_checked).ok();
+        let products: Vec<Product> = products
+            .into_iter()
+            .filter(
+                |p| match (DateTime::parse_from_rfc3339(&p.created), last_checked) {
+                    (Ok(created), Some(last_checked)) => created > last_checked,
+                    _ => false,
+                },
+            )
+            .collect();
+        follow.last_checked = chrono::Local::now().to_rfc3339();
+        if !products.is_empty() {
+            new_uploads.push((follow.personid.clone(), products));
+        }
+    }
+    // Someone may have followed or unfollowed while this ran, only the check times are ours
+    let mut latest = Follows::load();
+    for follow in latest.follows.iter_mut() {
+        if let Some(checked) = follows
+            .follows
+            .iter()
+            .find(|f| f.personid == follow.personid)
+        {
+            follow.last_checked = checked.last_checked.clone();
+        }
+    }
+    if let Err(e) = latest.save() {
+        eprintln!("Failed to save followed authors : {}", e);
+    }
+    new_uploads
+}
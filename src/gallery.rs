@@ -0,0 +1,379 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use adw::glib::object::IsA;
+use adw::gtk::prelude::{
+    BoxExt, ButtonExt, EventControllerExt, GestureExt, GtkWindowExt, WidgetExt,
+};
+use adw::gtk::{
+    gdk, glib, Align, Box as GtkBox, Button, ContentFit, EventControllerKey, EventControllerScroll,
+    EventControllerScrollFlags, GestureClick, GestureZoom, Image, Orientation, Picture, PolicyType,
+    ScrolledWindow,
+};
+use adw::prelude::AdwWindowExt;
+use adw::{ApplicationWindow, Carousel, CarouselIndicatorDots, HeaderBar, StatusPage};
+
+use crate::Result;
+
+const PREVIEW_CACHE_DIR: &str = "/tmp/themeinstaller/cache/";
+const MIN_ZOOM: f64 = 1.0;
+const MAX_ZOOM: f64 = 8.0;
+const ZOOM_STEP: f64 = 1.25;
+
+// Where a preview is cached, the URL path is kept so names never clash
+pub fn preview_path(url: &str) -> PathBuf {
+    PathBuf::from(PREVIEW_CACHE_DIR.to_string() + url)
+}
+
+// Listings link 770x540 renditions from the image cache, the original sits at the
+// same path without the cache prefix
+pub fn full_size_url(url: &str) -> String {
+    let start = match url.find("/cache/") {
+        Some(start) => start,
+        None => return url.to_string(),
+    };
+    let rendition = &url[start + "/cache/".len()..];
+    match rendition.split_once('/') {
+        Some((size, rest)) if size.contains('x') => format!("{}/{}", &url[..start], rest),
+        _ => url.to_string(),
+    }
+}
+
+// Blocking, downloads a preview unless it is cached already
+pub fn download_preview(url: &str) -> Result<PathBuf> {
+    let path = preview_path(url);
+    if path.exists() {
+        return Ok(path);
+    }
+    let content = reqwest::blocking::get(url)?.error_for_status()?.bytes()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Pictures loading the cache never see half an image
+    let part_path = path.with_extension("part");
+    fs::write(&part_path, &content)?;
+    fs::rename(&part_path, &path)?;
+    Ok(path)
+}
+
+// Shows `url` in `picture` once it is downloaded, `fallback` is shown when it can't be
+fn load_preview(picture: &Picture, url: &str, fallback: Option<String>) {
+    let path = preview_path(url);
+    if path.exists() {
+        picture.set_filename(Some(&path));
+        return;
+    }
+    let (sender, receiver) = async_channel::bounded::<Option<PathBuf>>(1);
+    let url = url.to_string();
+    adw::gio::spawn_blocking(move || {
+        let path = download_preview(&url)
+            .or_else(|e| {
+                eprintln!("Failed to download preview {} : {}", url, e);
+                fallback
+                    .ok_or(e)
+                    .and_then(|fallback| download_preview(&fallback))
+            })
+            .ok();
+        sender.send_blocking(path).unwrap_or_default();
+    });
+    let picture = picture.clone();
+    glib::spawn_future_local(async move {
+        if let Ok(Some(path)) = receiver.recv().await {
+            picture.set_filename(Some(&path));
+        }
+    });
+}
+
+// Loads the preview of a carousel page the first time it is shown
+fn connect_lazy_loading(
+    carousel: &Carousel,
+    pictures: Vec<Picture>,
+    urls: Vec<(String, Option<String>)>,
+) {
+    let loaded = Rc::new(RefCell::new(HashSet::new()));
+    let load_page = move |index: usize| {
+        // The neighbours too, so swiping never shows an empty page
+        for index in [index, index + 1, index.saturating_sub(1)] {
+            if index < pictures.len() && loaded.borrow_mut().insert(index) {
+                let (url, fallback) = &urls[index];
+                load_preview(&pictures[index], url, fallback.clone());
+            }
+        }
+    };
+    load_page(0);
+    carousel.connect_page_changed(move |_, index| load_page(index as usize));
+}
+
+fn scroll_by(carousel: &Carousel, offset: i32) {
+    let pages = carousel.n_pages() as i32;
+    if pages == 0 {
+        return;
+    }
+    let index = (carousel.position().round() as i32 + offset).rem_euclid(pages);
+    carousel.scroll_to(&carousel.nth_page(index as u32), true);
+}
+
+fn navigation_button(icon_name: &str, carousel: &Carousel, offset: i32) -> Button {
+    let button = Button::builder()
+        .icon_name(icon_name)
+        .css_classes(vec!["circular"])
+        .valign(Align::Center)
+        .build();
+    let carousel = carousel.clone();
+    button.connect_clicked(move |_| scroll_by(&carousel, offset));
+    button
+}
+
+// Left and right arrow keys page through `carousel` while `widget` has the focus
+fn connect_arrow_keys(widget: &impl IsA<adw::gtk::Widget>, carousel: &Carousel) {
+    let keys = EventControllerKey::new();
+    let carousel = carousel.clone();
+    keys.connect_key_pressed(move |_, key, _, _| match key {
+        gdk::Key::Left => {
+            scroll_by(&carousel, -1);
+            glib::Propagation::Stop
+        }
+        gdk::Key::Right => {
+            scroll_by(&carousel, 1);
+            glib::Propagation::Stop
+        }
+        _ => glib::Propagation::Proceed,
+    });
+    widget.add_controller(keys);
+}
+
+// Preview carousel of the detail dialog. Clicking a preview opens the fullscreen viewer.
+pub fn build_preview_gallery(previewpics: &[String], window: &ApplicationWindow) -> GtkBox {
+    let gallerybox = GtkBox::new(Orientation::Vertical, 10);
+    gallerybox.set_margin_top(20);
+    gallerybox.set_margin_bottom(20);
+    if previewpics.is_empty() {
+        gallerybox.append(
+            &StatusPage::builder()
+                .icon_name("image-missing-symbolic")
+                .title("No Previews")
+                .description("The author did not upload any screenshots")
+                .css_classes(vec!["compact"])
+                .build(),
+        );
+        return gallerybox;
+    }
+
+    let carousel = Carousel::builder()
+        .hexpand(true)
+        .spacing(20)
+        .allow_scroll_wheel(false)
+        .focusable(true)
+        .build();
+    let mut pictures = vec![];
+    for (index, _) in previewpics.iter().enumerate() {
+        let picture = Picture::builder()
+            .content_fit(ContentFit::Contain)
+            .height_request(420)
+            .hexpand(true)
+            .css_classes(vec!["img-cover"])
+            .build();
+        let click = GestureClick::new();
+        let previewpics = previewpics.to_vec();
+        let window = window.clone();
+        click.connect_released(move |click, _, _, _| {
+            click.set_state(adw::gtk::EventSequenceState::Claimed);
+            show_preview_viewer(&previewpics, index, &window);
+        });
+        picture.add_controller(click);
+        carousel.append(&picture);
+        pictures.push(picture);
+    }
+    let urls = previewpics.iter().map(|url| (url.clone(), None)).collect();
+    connect_lazy_loading(&carousel, pictures, urls);
+
+    let carouselbox = GtkBox::new(Orientation::Horizontal, 10);
+    carouselbox.append(&navigation_button("go-previous-symbolic", &carousel, -1));
+    carouselbox.append(&carousel);
+    carouselbox.append(&navigation_button("go-next-symbolic", &carousel, 1));
+    gallerybox.append(&carouselbox);
+    if previewpics.len() > 1 {
+        gallerybox.append(&CarouselIndicatorDots::builder().carousel(&carousel).build());
+    }
+    connect_arrow_keys(&gallerybox, &carousel);
+    gallerybox
+}
+
+// Fullscreen window with the original size previews. Pinch, Ctrl+scroll or +/- zoom,
+// arrow keys and swipes page, Escape closes.
+pub fn show_preview_viewer(previewpics: &[String], index: usize, window: &ApplicationWindow) {
+    let viewer = adw::Window::builder()
+        .transient_for(window)
+        .modal(true)
+        .title("Previews")
+        .build();
+    let carousel = Carousel::builder()
+        .hexpand(true)
+        .vexpand(true)
+        .allow_scroll_wheel(false)
+        .build();
+    let zoom = Rc::new(Cell::new(MIN_ZOOM));
+    let mut pictures = vec![];
+    let mut scrollwindows = vec![];
+    for _ in previewpics {
+        let picture = Picture::builder()
+            .content_fit(ContentFit::Contain)
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        let scrollwindow = ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .hscrollbar_policy(PolicyType::Automatic)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .child(&picture)
+            .build();
+        carousel.append(&scrollwindow);
+        pictures.push(picture);
+        scrollwindows.push(scrollwindow);
+    }
+    let urls = previewpics
+        .iter()
+        .map(|url| (full_size_url(url), Some(url.clone())))
+        .collect();
+    connect_lazy_loading(&carousel, pictures.clone(), urls);
+
+    // Zoom is relative to the fitted size, so 1.0 always shows the whole preview
+    let set_zoom = {
+        let zoom = zoom.clone();
+        let carousel = carousel.clone();
+        let pictures = pictures.clone();
+        let scrollwindows = scrollwindows.clone();
+        Rc::new(move |value: f64| {
+            zoom.set(value.clamp(MIN_ZOOM, MAX_ZOOM));
+            let index = carousel.position().round() as usize;
+            let (picture, scrollwindow) = match (pictures.get(index), scrollwindows.get(index)) {
+                (Some(picture), Some(scrollwindow)) => (picture, scrollwindow),
+                _ => return,
+            };
+            match zoom.get() > MIN_ZOOM {
+                true => picture.set_size_request(
+                    (scrollwindow.width() as f64 * zoom.get()) as i32,
+                    (scrollwindow.height() as f64 * zoom.get()) as i32,
+                ),
+                false => picture.set_size_request(-1, -1),
+            }
+            // Swiping would fight with panning a zoomed preview
+            carousel.set_interactive(zoom.get() == MIN_ZOOM);
+        })
+    };
+    let reset_pictures = pictures.clone();
+    let page_zoom = zoom.clone();
+    carousel.connect_page_changed(move |carousel, _| {
+        for picture in &reset_pictures {
+            picture.set_size_request(-1, -1);
+        }
+        page_zoom.set(MIN_ZOOM);
+        carousel.set_interactive(true);
+    });
+
+    let pinch = GestureZoom::new();
+    let pinch_start = Rc::new(Cell::new(MIN_ZOOM));
+    let begin_zoom = zoom.clone();
+    let begin_start = pinch_start.clone();
+    pinch.connect_begin(move |_, _| begin_start.set(begin_zoom.get()));
+    let pinch_set_zoom = set_zoom.clone();
+    pinch.connect_scale_changed(move |_, scale| pinch_set_zoom(pinch_start.get() * scale));
+    carousel.add_controller(pinch);
+
+    let scroll = EventControllerScroll::new(EventControllerScrollFlags::VERTICAL);
+    let scroll_zoom = zoom.clone();
+    let scroll_set_zoom = set_zoom.clone();
+    scroll.connect_scroll(move |scroll, _, dy| {
+        if !scroll
+            .current_event_state()
+            .contains(gdk::ModifierType::CONTROL_MASK)
+        {
+            return glib::Propagation::Proceed;
+        }
+        match dy < 0.0 {
+            true => scroll_set_zoom(scroll_zoom.get() * ZOOM_STEP),
+            false => scroll_set_zoom(scroll_zoom.get() / ZOOM_STEP),
+        }
+        glib::Propagation::Stop
+    });
+    carousel.add_controller(scroll);
+
+    let header = HeaderBar::new();
+    let zoom_button = |icon_name: &str, tooltip: &str, change: fn(f64) -> f64| {
+        let button = Button::builder()
+            .icon_name(icon_name)
+            .tooltip_text(tooltip)
+            .build();
+        let zoom = zoom.clone();
+        let set_zoom = set_zoom.clone();
+        button.connect_clicked(move |_| set_zoom(change(zoom.get())));
+        button
+    };
+    header.pack_start(&zoom_button("zoom-out-symbolic", "Zoom Out", |z| {
+        z / ZOOM_STEP
+    }));
+    header.pack_start(&zoom_button("zoom-original-symbolic", "Fit", |_| MIN_ZOOM));
+    header.pack_start(&zoom_button("zoom-in-symbolic", "Zoom In", |z| {
+        z * ZOOM_STEP
+    }));
+    if previewpics.len() > 1 {
+        header.set_title_widget(Some(
+            &CarouselIndicatorDots::builder().carousel(&carousel).build(),
+        ));
+    }
+
+    let keys = EventControllerKey::new();
+    let key_viewer = viewer.clone();
+    let key_carousel = carousel.clone();
+    keys.connect_key_pressed(move |_, key, _, _| {
+        match key {
+            gdk::Key::Escape => key_viewer.close(),
+            gdk::Key::Left => scroll_by(&key_carousel, -1),
+            gdk::Key::Right => scroll_by(&key_carousel, 1),
+            gdk::Key::plus | gdk::Key::equal | gdk::Key::KP_Add => set_zoom(zoom.get() * ZOOM_STEP),
+            gdk::Key::minus | gdk::Key::KP_Subtract => set_zoom(zoom.get() / ZOOM_STEP),
+            gdk::Key::_0 | gdk::Key::KP_0 => set_zoom(MIN_ZOOM),
+            _ => return glib::Propagation::Proceed,
+        }
+        glib::Propagation::Stop
+    });
+    viewer.add_controller(keys);
+
+    let viewerbox = GtkBox::new(Orientation::Vertical, 0);
+    viewerbox.append(&header);
+    viewerbox.append(&carousel);
+    if previewpics.len() > 1 {
+        let navigationbox = GtkBox::new(Orientation::Horizontal, 20);
+        navigationbox.set_halign(Align::Center);
+        navigationbox.set_margin_top(10);
+        navigationbox.set_margin_bottom(10);
+        navigationbox.append(&navigation_button("go-previous-symbolic", &carousel, -1));
+        navigationbox.append(&navigation_button("go-next-symbolic", &carousel, 1));
+        viewerbox.append(&navigationbox);
+    }
+    viewer.set_content(Some(&viewerbox));
+    viewer.fullscreen();
+    viewer.present();
+
+    // Pages only have a size once the window is shown
+    if let Some(page) = scrollwindows.get(index) {
+        let page = page.clone();
+        let carousel = carousel.clone();
+        glib::idle_add_local_once(move || carousel.scroll_to(&page, false));
+    }
+}
+
+// Card thumbnail stand-in for products without previews
+pub fn missing_preview() -> Image {
+    Image::builder()
+        .icon_name("image-missing-symbolic")
+        .pixel_size(96)
+        .css_classes(vec!["dimmed"])
+        .height_request(260)
+        .width_request(260)
+        .build()
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use adw::glib;
+
+use crate::storage::{data_dir, load_json, save_json};
+use crate::Result;
+
+// What libadwaita reads from ~/.config/gtk-4.0 on top of its own stylesheet
+const MANAGED_ITEMS: [&str; 3] = ["gtk.css", "gtk-dark.css", "assets"];
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LinkMode {
+    #[default]
+    Link,
+    Copy,
+}
+
+// Which theme currently lives in ~/.config/gtk-4.0 and where the user's own files went
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Gtk4ThemeState {
+    pub theme_dir: String,
+    pub mode: LinkMode,
+    pub backup_dir: String,
+    // Items of MANAGED_ITEMS this tool placed into ~/.config/gtk-4.0
+    pub items: Vec<String>,
+}
+
+impl Gtk4ThemeState {
+    fn path() -> PathBuf {
+        data_dir().join("gtk4-theme.json")
+    }
+    pub fn load() -> Option<Gtk4ThemeState> {
+        let state: Gtk4ThemeState = load_json(&Gtk4ThemeState::path());
+        match state.theme_dir.is_empty() {
+            true => None,
+            false => Some(state),
+        }
+    }
+    fn save(&self) -> Result<()> {
+        save_json(&Gtk4ThemeState::path(), self)
+    }
+    pub fn theme_name(&self) -> String {
+        Path::new(&self.theme_dir)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+pub fn gtk4_config_dir() -> PathBuf {
+    glib::user_config_dir().join("gtk-4.0")
+}
+
+// Links or copies the gtk-4.0 assets of an installed theme into ~/.config/gtk-4.0.
+// Whatever was there before is moved aside and comes back with revert_gtk4_theme.
+pub fn apply_gtk4_theme(theme_dir: &Path, mode: &LinkMode) -> Result<()> {
+    let source_dir = theme_dir.join("gtk-4.0");
+    if !source_dir.join("gtk.css").exists() {
+        return Err(format!("{} has no gtk-4.0/gtk.css", theme_dir.display()).into());
+    }
+    // Switching between store themes must not back up our own links
+    if Gtk4ThemeState::load().is_some() {
+        revert_gtk4_theme()?;
+    }
+
+    let config_dir = gtk4_config_dir();
+    fs::create_dir_all(&config_dir)?;
+    let backup_dir = data_dir()
+        .join("gtk4-backup")
+        .join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+
+    let mut state = Gtk4ThemeState {
+        theme_dir: theme_dir.display().to_string(),
+        mode: mode.clone(),
+        backup_dir: backup_dir.display().to_string(),
+        items: vec![],
+    };
+    for item in MANAGED_ITEMS {
+        let source = source_dir.join(item);
+        if !source.exists() {
+            continue;
+        }
+        if let Err(e) = place_item(&source, &config_dir.join(item), item, &mut state) {
+            // The state knows every item moved aside so far, put them all back
+            if let Err(revert_error) = revert_gtk4_theme() {
+                eprintln!("Failed to undo the gtk-4.0 changes : {}", revert_error);
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+// Moves the user's item aside and puts the theme's in its place. The state is saved
+// before anything is placed, so a half applied theme can always be reverted.
+fn place_item(source: &Path, target: &Path, item: &str, state: &mut Gtk4ThemeState) -> Result<()> {
+    if target.symlink_metadata().is_ok() {
+        let backup_dir = PathBuf::from(&state.backup_dir);
+        fs::create_dir_all(&backup_dir)?;
+        fs::rename(target, backup_dir.join(item))?;
+    }
+    state.items.push(item.to_string());
+    state.save()?;
+    match state.mode {
+        LinkMode::Link => std::os::unix::fs::symlink(source, target)?,
+        LinkMode::Copy => copy_recursive(source, target)?,
+    }
+    Ok(())
+}
+
+// Removes what apply_gtk4_theme placed and puts the user's previous files back
+pub fn revert_gtk4_theme() -> Result<()> {
+    let state = match Gtk4ThemeState::load() {
+        Some(state) => state,
+        None => return Ok(()),
+    };
+    let config_dir = gtk4_config_dir();
+    let backup_dir = PathBuf::from(&state.backup_dir);
+    for item in &state.items {
+        let target = config_dir.join(item);
+        if let Ok(metadata) = target.symlink_metadata() {
+            if metadata.is_dir() {
+                fs::remove_dir_all(&target)?;
+            } else {
+                fs::remove_file(&target)?;
+            }
+        }
+        let backup = backup_dir.join(item);
+        if backup.symlink_metadata().is_ok() {
+            fs::rename(&backup, &target)?;
+        }
+    }
+    let _ = fs::remove_dir(&backup_dir);
+    fs::remove_file(Gtk4ThemeState::path())?;
+    Ok(())
+}
+
+fn copy_recursive(source: &Path, target: &Path) -> Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(source, target)?;
+    }
+    Ok(())
+}
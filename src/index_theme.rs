@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use adw::glib;
+
+// Parsed freedesktop index.theme, enough for inheritance and directory lookups
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct IndexTheme {
+    pub name: String,
+    pub inherits: Vec<String>,
+    pub directories: Vec<String>,
+    // (section, key, value) in file order
+    pub entries: Vec<(String, String, String)>,
+}
+
+impl IndexTheme {
+    pub fn parse(content: &str) -> IndexTheme {
+        let mut index_theme = IndexTheme::default();
+        let mut section = String::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].to_string();
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                index_theme.entries.push((
+                    section.clone(),
+                    key.trim().to_string(),
+                    value.trim().to_string(),
+                ));
+            }
+        }
+        index_theme.name = index_theme.get("Icon Theme", "Name").unwrap_or_default();
+        index_theme.inherits = index_theme.get_list("Icon Theme", "Inherits");
+        index_theme.directories = index_theme.get_list("Icon Theme", "Directories");
+        index_theme
+            .directories
+            .extend(index_theme.get_list("Icon Theme", "ScaledDirectories"));
+        index_theme
+    }
+
+    pub fn load(theme_dir: &Path) -> Option<IndexTheme> {
+        let content = fs::read_to_string(theme_dir.join("index.theme")).ok()?;
+        Some(IndexTheme::parse(&content))
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<String> {
+        self.entries
+            .iter()
+            .find(|(s, k, _)| s == section && k == key)
+            .map(|(_, _, v)| v.clone())
+    }
+
+    pub fn get_list(&self, section: &str, key: &str) -> Vec<String> {
+        self.get(section, key)
+            .unwrap_or_default()
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+}
+
+// Icon theme base directories in lookup order, as GTK searches them
+pub fn icon_search_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![glib::user_data_dir().join("icons")];
+    if let Ok(home_dir) = std::env::var("HOME") {
+        dirs.push(PathBuf::from(home_dir).join(".icons"));
+    }
+    for data_dir in glib::system_data_dirs() {
+        dirs.push(data_dir.join("icons"));
+    }
+    dirs.push(PathBuf::from("/usr/share/icons"));
+    dirs.dedup();
+    dirs
+}
+
+pub fn find_installed_icon_theme(name: &str) -> Option<PathBuf> {
+    icon_search_dirs()
+        .into_iter()
+        .map(|dir| dir.join(name))
+        .find(|theme_dir| theme_dir.join("index.theme").exists())
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct InheritanceReport {
+    pub missing: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+// Walks the Inherits= chain of the theme in `theme_dir` and reports parents
+// that are not installed, circular chains and parents without an index.theme
+pub fn check_inheritance(theme_dir: &Path) -> InheritanceReport {
+    let mut report = InheritanceReport::default();
+    let theme_name = theme_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let index_theme = match IndexTheme::load(theme_dir) {
+        Some(index_theme) => index_theme,
+        None => {
+            report
+                .warnings
+                .push(format!("{} has no index.theme", theme_name));
+            return report;
+        }
+    };
+    let mut chain = vec![theme_name];
+    let mut visited: Vec<String> = vec![];
+    walk_parents(&index_theme.inherits, &mut chain, &mut visited, &mut report);
+    report
+}
+
+fn walk_parents(
+    parents: &[String],
+    chain: &mut Vec<String>,
+    visited: &mut Vec<String>,
+    report: &mut InheritanceReport,
+) {
+    for parent in parents {
+        if chain.contains(parent) {
+            report.warnings.push(format!(
+                "Circular inheritance : {} -> {}",
+                chain.join(" -> "),
+                parent
+            ));
+            continue;
+        }
+        if visited.contains(parent) {
+            continue;
+        }
+        visited.push(parent.clone());
+
+        let parent_dir = match find_installed_icon_theme(parent) {
+            Some(parent_dir) => parent_dir,
+            None => {
+                if !report.missing.contains(parent) {
+                    report.missing.push(parent.clone());
+                }
+                continue;
+            }
+        };
+        match IndexTheme::load(&parent_dir) {
+            Some(parent_index) => {
+                chain.push(parent.clone());
+                walk_parents(&parent_index.inherits, chain, visited, report);
+                chain.pop();
+            }
+            None => report.warnings.push(format!(
+                "{} has no readable index.theme",
+                parent_dir.display()
+            )),
+        }
+    }
+}
@@ -0,0 +1,846 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::archive_cache::{archive_path, cached_archive, download_to_cache, fetch_archive};
+use crate::desktop::{icon_cache_tool, refresh_icon_cache};
+use crate::index_theme::check_inheritance;
+use crate::manifest::{InstallManifest, InstallRecord};
+use crate::storage::{cache_dir, format_size, free_space};
+use crate::validator::{validate_theme, ValidationReport};
+use crate::{
+    get_search_product_catalog, Catalog, DownloadDetail, Product, Result, SearchPageProps,
+};
+
+// Compressed themes, icon themes especially, unpack to several times their archive size
+const EXTRACTED_SIZE_FACTOR: u64 = 4;
+// Hidden directories inside the install location, on the same filesystem as the
+// installed themes so moving between them is a rename rather than a copy
+const STAGING_DIR_PREFIX: &str = ".linuxthemestore-staging-";
+const BACKUP_DIR_NAME: &str = ".linuxthemestore-backup";
+static INSTALL_LOCK: Mutex<()> = Mutex::new(());
+// Everything a system wide install does as root, so it takes a single pkexec prompt.
+// $1 extracted directories owned by the user, $2 staging next to the themes,
+// $3 location, $4 backup directory, $5 icon cache tool, "remove" or "skip",
+// then the theme directories. Keeps one backup per directory, a stale one is
+// dropped when nothing is replaced. Icon cache failures are only reported.
+const ELEVATED_INSTALL_SCRIPT: &str = r#"set -e
+source="$1"; staging="$2"; location="$3"; backups="$4"; cache="$5"; shift 5
+trap 'rm -rf -- "${staging:?}"' EXIT
+mkdir -p -- "${location:?}" "${backups:?}"
+rm -rf -- "${staging:?}"
+mkdir -- "$staging"
+for dir in "$@"; do
+    cp -RP -- "${source:?}/${dir:?}" "$staging/$dir"
+done
+for dir in "$@"; do
+    rm -rf -- "${backups:?}/${dir:?}"
+    if [ -e "$location/$dir" ] || [ -L "$location/$dir" ]; then
+        mv -- "${location:?}/${dir:?}" "$backups/$dir"
+    fi
+    mv -- "${staging:?}/${dir:?}" "$location/$dir"
+done
+[ "$cache" = skip ] && exit 0
+for dir in "$@"; do
+    if [ "$cache" = remove ]; then
+        rm -f -- "$location/$dir/icon-theme.cache"
+    elif ! "$cache" -f -t -q "$location/$dir"; then
+        echo "Failed to update icon cache for $dir" >&2
+    fi
+done
+exit 0"#;
+// $1 location, $2 backup directory, then the theme directories
+const ELEVATED_ROLLBACK_SCRIPT: &str = r#"set -e
+location="$1"; backups="$2"; shift 2
+for dir in "$@"; do
+    rm -rf -- "${location:?}/${dir:?}"
+    if [ -e "$backups/$dir" ] || [ -L "$backups/$dir" ]; then
+        mv -- "${backups:?}/${dir:?}" "$location/$dir"
+    fi
+done"#;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InstallScope {
+    // ~/.local/share/themes, ~/.local/share/icons
+    #[default]
+    UserXdg,
+    // ~/.themes, ~/.icons for GTK2 apps and older desktops
+    UserLegacy,
+    // /usr/share/themes, /usr/share/icons through pkexec
+    System,
+}
+impl InstallScope {
+    pub fn to_string(&self) -> &str {
+        match &self {
+            InstallScope::UserXdg => "Current User",
+            InstallScope::UserLegacy => "Current User (Legacy)",
+            InstallScope::System => "All Users",
+        }
+    }
+    pub fn get_all_scopes() -> Vec<InstallScope> {
+        vec![
+            InstallScope::UserXdg,
+            InstallScope::UserLegacy,
+            InstallScope::System,
+        ]
+    }
+    pub fn is_elevated(&self) -> bool {
+        *self == InstallScope::System
+    }
+    pub fn target_dir(&self, catalog: &Catalog) -> Result<PathBuf> {
+        let home_dir = PathBuf::from(std::env::var("HOME")?);
+        let target = match (self, catalog) {
+            (InstallScope::UserXdg, Catalog::FullIconThemes | Catalog::Cursors) => {
+                home_dir.join(".local/share/icons")
+            }
+            (InstallScope::UserXdg, Catalog::Gtk4Themes | Catalog::GnomeShellThemes) => {
+                home_dir.join(".local/share/themes")
+            }
+            // Plasma never had a legacy dot directory
+            (InstallScope::UserXdg | InstallScope::UserLegacy, Catalog::KDEThemes) => {
+                home_dir.join(".local/share/plasma/desktoptheme")
+            }
+            (InstallScope::UserLegacy, Catalog::FullIconThemes | Catalog::Cursors) => {
+                home_dir.join(".icons")
+            }
+            (InstallScope::UserLegacy, Catalog::Gtk4Themes | Catalog::GnomeShellThemes) => {
+                home_dir.join(".themes")
+            }
+            (InstallScope::System, Catalog::FullIconThemes | Catalog::Cursors) => {
+                PathBuf::from("/usr/share/icons")
+            }
+            (InstallScope::System, Catalog::Gtk4Themes | Catalog::GnomeShellThemes) => {
+                PathBuf::from("/usr/share/themes")
+            }
+            (InstallScope::System, Catalog::KDEThemes) => {
+                PathBuf::from("/usr/share/plasma/desktoptheme")
+            }
+        };
+        Ok(target)
+    }
+}
+
+// What to do when a theme directory already exists and belongs to another product
+// or was installed by hand
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConflictResolution {
+    // Stop with a ConflictError so the user can decide
+    Ask,
+    // Overwrite, the replaced directory is kept as a backup for rollback
+    Replace,
+    // Install the conflicting directories under a name with the product id appended
+    Rename,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub directory: String,
+    // Name of the store product that installed the directory, None for manual installs
+    pub owner: Option<String>,
+}
+
+impl Conflict {
+    pub fn describe(&self) -> String {
+        match &self.owner {
+            Some(owner) => format!("{} belongs to {}", self.directory, owner),
+            None => format!("{} was installed manually", self.directory),
+        }
+    }
+}
+
+// Returned by install_theme with ConflictResolution::Ask, nothing was changed yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictError(pub Vec<Conflict>);
+
+impl std::fmt::Display for ConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let conflicts: Vec<String> = self.0.iter().map(|c| c.describe()).collect();
+        write!(f, "Already installed : {}", conflicts.join(", "))
+    }
+}
+
+impl std::error::Error for ConflictError {}
+
+// Name a conflicting directory is installed under with ConflictResolution::Rename
+pub fn renamed_directory(directory: &str, product: &Product) -> String {
+    format!("{}-{}", directory, product.id)
+}
+
+// Existing directories at `location` an install of `product` would overwrite.
+// Reinstalling or updating the same product is not a conflict.
+pub fn find_conflicts(product: &Product, directories: &[String], location: &Path) -> Vec<Conflict> {
+    let manifest = InstallManifest::load();
+    let location_string = location.display().to_string();
+    directories
+        .iter()
+        .filter(|directory| exists(&location.join(directory)))
+        .filter_map(|directory| {
+            let owner = manifest
+                .records
+                .iter()
+                .find(|r| r.location == location_string && r.directories.contains(directory));
+            match owner {
+                Some(record) if record.productid == product.id => None,
+                Some(record) => Some(Conflict {
+                    directory: directory.clone(),
+                    owner: Some(record.name.clone()),
+                }),
+                None => Some(Conflict {
+                    directory: directory.clone(),
+                    owner: None,
+                }),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ArchiveKind {
+    Tar,
+    SevenZip,
+    Zip,
+}
+impl ArchiveKind {
+    fn from_path(path: &str) -> Option<ArchiveKind> {
+        if path.ends_with(".tar") || path.ends_with(".tar.xz") || path.ends_with(".tar.gz") {
+            Some(ArchiveKind::Tar)
+        } else if path.ends_with(".7z") {
+            Some(ArchiveKind::SevenZip)
+        } else if path.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+// What happened during an install, beyond the manifest record itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallOutcome {
+    pub record: InstallRecord,
+    pub validation: Vec<ValidationReport>,
+    // Inherits= parents of installed icon themes that are not on this machine
+    pub missing_parents: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+// Downloads a variant unless a complete copy of this revision is cached, returns the archive path
+pub fn download_archive(
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+    themetype: &Catalog,
+) -> Result<String> {
+    let path = fetch_archive(product, downloaddetail, themetype)?;
+    Ok(path.display().to_string())
+}
+
+// Fails before downloading when the archive or its extracted files would not fit.
+// The extracted size is unknown until the archive is here, so it is estimated.
+fn check_free_space(
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+    location: &Path,
+    cached: bool,
+) -> Result<()> {
+    let archive_size = downloaddetail.size_in_bytes();
+    if archive_size == 0 {
+        return Ok(());
+    }
+    let archive = archive_path(product, downloaddetail);
+    let download_needed = match cached {
+        true => 0,
+        false => archive_size,
+    };
+    let extract_needed = archive_size * EXTRACTED_SIZE_FACTOR;
+    let (download_fs, download_free) = free_space(&archive)?;
+    let (target_fs, target_free) = free_space(location)?;
+    let mut checks = vec![(location, target_free, extract_needed)];
+    match download_fs == target_fs {
+        true => checks[0].2 += download_needed,
+        false => checks.push((archive.as_path(), download_free, download_needed)),
+    }
+    for (path, free, needed) in checks {
+        if needed > free {
+            return Err(format!(
+                "Not enough space for {} : about {} needed on the drive of {}, {} free",
+                downloaddetail.downloadname,
+                format_size(needed),
+                path.display(),
+                format_size(free)
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+pub fn install_theme(
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+    themetype: &Catalog,
+    scope: &InstallScope,
+    resolution: &ConflictResolution,
+) -> Result<InstallOutcome> {
+    let location = scope.target_dir(themetype)?;
+    // Checked once, verifying a cached archive means hashing all of it
+    let cached = cached_archive(product, downloaddetail);
+    check_free_space(product, downloaddetail, &location, cached.is_some())?;
+    let path = match cached {
+        Some(path) => path,
+        None => download_to_cache(product, downloaddetail, themetype)?,
+    }
+    .display()
+    .to_string();
+    // Downloads run side by side, changing the theme directories does not
+    let _guard = INSTALL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let listed: Vec<String> = list_archive_directories(&path)?
+        .into_iter()
+        .filter(|d| is_plain_directory_name(d))
+        .collect();
+    let conflicts = find_conflicts(product, &listed, &location);
+    if !conflicts.is_empty() && *resolution == ConflictResolution::Ask {
+        return Err(Box::new(ConflictError(conflicts)));
+    }
+
+    if scope.is_elevated() && Path::new("/.flatpak-info").exists() {
+        return Err("System wide installs are not available inside the Flatpak sandbox".into());
+    }
+    // Extract and validate as the user, the desktop only ever sees complete directories.
+    // Next to the live themes when they are ours, in the cache for system wide ones,
+    // which the elevated script copies next to the themes itself.
+    let staging_name = format!(
+        "{}{}-{}",
+        STAGING_DIR_PREFIX,
+        std::process::id(),
+        chrono::Local::now()
+            .timestamp_nanos_opt()
+            .unwrap_or_default()
+    );
+    let staging = match scope.is_elevated() {
+        true => cache_dir().join(&staging_name),
+        false => location.join(&staging_name),
+    };
+    let directories: Vec<String> = match install_tar(&path, &staging) {
+        Ok(directories) => directories
+            .into_iter()
+            .filter(|d| is_plain_directory_name(d))
+            .collect(),
+        Err(e) => {
+            remove_staging(&staging);
+            return Err(e);
+        }
+    };
+    if directories.is_empty() {
+        remove_staging(&staging);
+        return Err("The archive did not contain a theme directory".into());
+    }
+    let directories = match resolution {
+        ConflictResolution::Rename => {
+            match rename_conflicts(&staging, directories, &conflicts, product) {
+                Ok(directories) => directories,
+                Err(e) => {
+                    remove_staging(&staging);
+                    return Err(e);
+                }
+            }
+        }
+        _ => directories,
+    };
+
+    let validation: Vec<ValidationReport> = directories
+        .iter()
+        .map(|directory| validate_theme(&staging.join(directory), themetype))
+        .collect();
+    let issues: Vec<String> = validation
+        .iter()
+        .flat_map(|report| {
+            report
+                .issues
+                .iter()
+                .map(move |issue| format!("{} : {}", report.directory, issue))
+        })
+        .collect();
+
+    let icon_theme = matches!(themetype, Catalog::FullIconThemes | Catalog::Cursors);
+    let replaced = match scope.is_elevated() {
+        true => replace_directories_elevated(
+            &staging,
+            &location.join(&staging_name),
+            &location,
+            &directories,
+            icon_theme,
+        ),
+        false => replace_directories(&staging, &location, &directories),
+    };
+    remove_staging(&staging);
+    let (backups, mut warnings) = replaced?;
+
+    let location_string = location.display().to_string();
+    let record = InstallManifest::update(|manifest| {
+        let replaces = |r: &InstallRecord| {
+            r.location == location_string && r.directories.iter().any(|d| directories.contains(d))
+        };
+        // Installs whose directories were just replaced, the first one comes back on rollback
+        let previous = match backups.is_empty() {
+            true => None,
+            false => manifest.records.iter().find(|r| replaces(r)).cloned(),
+        }
+        .map(|mut previous| {
+            // Its own backups are overwritten by this install's, it can not be rolled back again
+            previous.previous = None;
+            previous.backups.clear();
+            Box::new(previous)
+        });
+        manifest.records.retain(|r| !replaces(r));
+        let record = InstallRecord {
+            productid: product.id,
+            name: product.name.clone(),
+            catalog: themetype.clone(),
+            downloadname: downloaddetail.downloadname.clone(),
+            downloadmd5sum: downloaddetail.downloadmd5sum.clone(),
+            changed: product.changed.clone(),
+            scope: scope.clone(),
+            location: location_string.clone(),
+            directories: directories.clone(),
+            installed: chrono::Local::now().to_rfc3339(),
+            issues,
+            backups,
+            previous,
+        };
+        manifest.upsert(record.clone());
+        record
+    })?;
+
+    if icon_theme && !scope.is_elevated() {
+        let theme_dirs: Vec<PathBuf> = directories.iter().map(|d| location.join(d)).collect();
+        if let Err(e) = refresh_icon_cache(&theme_dirs, scope) {
+            warnings.push(e.to_string());
+        }
+    }
+    let mut outcome = InstallOutcome {
+        record,
+        validation,
+        missing_parents: vec![],
+        warnings,
+    };
+    if icon_theme {
+        let theme_dirs: Vec<PathBuf> = outcome
+            .record
+            .directories
+            .iter()
+            .map(|directory| location.join(directory))
+            .collect();
+        for theme_dir in &theme_dirs {
+            let report = check_inheritance(theme_dir);
+            for parent in report.missing {
+                if !outcome.missing_parents.contains(&parent) {
+                    outcome.missing_parents.push(parent);
+                }
+            }
+            outcome.warnings.extend(report.warnings);
+        }
+    }
+    Ok(outcome)
+}
+
+// Names from archive listings end up in paths, anything that could leave the location is skipped
+fn is_plain_directory_name(directory: &str) -> bool {
+    !directory.is_empty() && !directory.contains('/') && directory != "." && directory != ".."
+}
+
+// Best effort, a leftover staging directory is hidden and never picked up as a theme.
+// Staging always belongs to the user, removing it never needs a prompt.
+fn remove_staging(staging: &Path) {
+    if !staging.exists() {
+        return;
+    }
+    if let Err(e) = fs::remove_dir_all(staging) {
+        eprintln!("Failed to remove {} : {}", staging.display(), e);
+    }
+}
+
+fn exists(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    match path.symlink_metadata()?.is_dir() {
+        true => fs::remove_dir_all(path),
+        false => fs::remove_file(path),
+    }
+}
+
+// Gives the conflicting directories in staging their renamed names
+fn rename_conflicts(
+    staging: &Path,
+    directories: Vec<String>,
+    conflicts: &[Conflict],
+    product: &Product,
+) -> Result<Vec<String>> {
+    let mut renamed = vec![];
+    for directory in directories {
+        if !conflicts.iter().any(|c| c.directory == directory) {
+            renamed.push(directory);
+            continue;
+        }
+        let new_name = renamed_directory(&directory, product);
+        if let Err(e) = fs::rename(staging.join(&directory), staging.join(&new_name)) {
+            return Err(format!("Failed to rename {} : {}", directory, e).into());
+        }
+        renamed.push(new_name);
+    }
+    Ok(renamed)
+}
+
+// Moves the extracted directories from staging into place. Each replaced directory is
+// kept in the backup directory, returns the directories that had a previous version
+// and any warnings.
+// The old directory is renamed to the backup and the new one renamed into its place.
+// rename(2) can not swap two directories and renameat2(RENAME_EXCHANGE) is neither in
+// std nor in sh, so for the moment between the two renames the theme is missing and
+// a lookup right then falls back to the default theme.
+fn replace_directories(
+    staging: &Path,
+    location: &Path,
+    directories: &[String],
+) -> Result<(Vec<String>, Vec<String>)> {
+    let backup_dir = location.join(BACKUP_DIR_NAME);
+    let backups: Vec<String> = directories
+        .iter()
+        .filter(|directory| exists(&location.join(directory)))
+        .cloned()
+        .collect();
+
+    fs::create_dir_all(&backup_dir)?;
+    // Directories already swapped, undone again when a later one fails
+    let mut replaced: Vec<&String> = vec![];
+    for directory in directories {
+        let target = location.join(directory);
+        let backup = backup_dir.join(directory);
+        let had_backup = backups.contains(directory);
+        let result = (|| -> std::io::Result<()> {
+            if exists(&backup) {
+                remove_path(&backup)?;
+            }
+            if had_backup {
+                fs::rename(&target, &backup)?;
+            }
+            if let Err(e) = fs::rename(staging.join(directory), &target) {
+                if had_backup {
+                    fs::rename(&backup, &target)?;
+                }
+                return Err(e);
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            for directory in replaced {
+                let target = location.join(directory);
+                let _ = remove_path(&target);
+                if backups.contains(directory) {
+                    let _ = fs::rename(backup_dir.join(directory), &target);
+                }
+            }
+            return Err(format!("Failed to move {} into place : {}", directory, e).into());
+        }
+        replaced.push(directory);
+    }
+    Ok((backups, vec![]))
+}
+
+// replace_directories for system wide installs: copies the user's staging next to the
+// themes, swaps them in the same way and refreshes icon caches, all under one prompt
+fn replace_directories_elevated(
+    source: &Path,
+    staging: &Path,
+    location: &Path,
+    directories: &[String],
+    icon_theme: bool,
+) -> Result<(Vec<String>, Vec<String>)> {
+    let backups: Vec<String> = directories
+        .iter()
+        .filter(|directory| exists(&location.join(directory)))
+        .cloned()
+        .collect();
+    let cache = match (icon_theme, icon_cache_tool()) {
+        (false, _) => String::from("skip"),
+        (true, Some(tool)) => tool.display().to_string(),
+        (true, None) => String::from("remove"),
+    };
+    let output = Command::new("pkexec")
+        .arg("sh")
+        .arg("-c")
+        .arg(ELEVATED_INSTALL_SCRIPT)
+        .arg("sh")
+        .arg(source)
+        .arg(staging)
+        .arg(location)
+        .arg(location.join(BACKUP_DIR_NAME))
+        .arg(&cache)
+        .args(directories)
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if !output.status.success() {
+        return Err(format!("Failed to move the theme into place : {}", stderr).into());
+    }
+    let warnings = stderr.lines().map(String::from).collect();
+    Ok((backups, warnings))
+}
+
+// One step back: puts the versions an install replaced into place again and restores
+// their manifest entry, directories it added alongside them are removed. Refused for
+// installs that replaced nothing, those are uninstalled instead.
+pub fn rollback_install(record: &InstallRecord) -> Result<()> {
+    let location = PathBuf::from(&record.location);
+    let backup_dir = location.join(BACKUP_DIR_NAME);
+    let directories: Vec<&String> = record
+        .directories
+        .iter()
+        .filter(|d| is_plain_directory_name(d))
+        .collect();
+    // Without every backup the live directories would be removed with nothing to put back
+    let missing: Vec<&String> = record
+        .backups
+        .iter()
+        .filter(|d| !is_plain_directory_name(d) || !exists(&backup_dir.join(d)))
+        .collect();
+    if record.backups.is_empty() || !missing.is_empty() {
+        return Err(format!(
+            "Nothing to roll back {} to, the backup is gone",
+            record.name
+        )
+        .into());
+    }
+    if record.scope.is_elevated() {
+        let output = Command::new("pkexec")
+            .arg("sh")
+            .arg("-c")
+            .arg(ELEVATED_ROLLBACK_SCRIPT)
+            .arg("sh")
+            .arg(&location)
+            .arg(&backup_dir)
+            .args(&directories)
+            .output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to roll back {} : {}",
+                record.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+    } else {
+        for directory in directories {
+            let target = location.join(directory);
+            let backup = backup_dir.join(directory);
+            if exists(&target) {
+                remove_path(&target)?;
+            }
+            if exists(&backup) {
+                fs::rename(&backup, &target)?;
+            }
+        }
+    }
+    InstallManifest::update(|manifest| {
+        manifest.remove(record);
+        if let Some(previous) = &record.previous {
+            manifest.upsert(previous.as_ref().clone());
+        }
+    })
+}
+
+// Looks up each missing parent by name in the store catalog of the theme that
+// inherits from it, icon themes among icon themes and cursors among cursors, and
+// installs its best matching variant. Returns one message per parent.
+pub fn install_missing_parents(
+    parents: &[String],
+    catalog: &Catalog,
+    scope: &InstallScope,
+) -> Vec<std::result::Result<String, String>> {
+    let mut results = vec![];
+    for parent in parents {
+        let result = match find_store_theme(parent, catalog) {
+            Ok(Some((product, downloaddetail))) => install_theme(
+                &product,
+                &downloaddetail,
+                catalog,
+                scope,
+                &ConflictResolution::Replace,
+            )
+            .map(|outcome| format!("Installed {} for {}", outcome.record.name, parent))
+            .map_err(|e| format!("Failed to install {} : {}", parent, e)),
+            Ok(None) => Err(format!("{} was not found in the store", parent)),
+            Err(e) => Err(format!("Failed to search for {} : {}", parent, e)),
+        };
+        results.push(result);
+    }
+    results
+}
+
+fn find_store_theme(name: &str, catalog: &Catalog) -> Result<Option<(Product, DownloadDetail)>> {
+    let product = match find_store_product(name, catalog)? {
+        Some(product) => product,
+        None => return Ok(None),
+    };
+    // Prefer the file named after the theme over extras like wallpapers or sources
+    let lowercase_name = name.to_lowercase();
+    let downloaddetail = product
+        .downloaddetails
+        .iter()
+        .find(|d| d.downloadname.to_lowercase().contains(&lowercase_name))
+        .unwrap_or(&product.downloaddetails[0])
+        .clone();
+    Ok(Some((product, downloaddetail)))
+}
+
+// Store product of a catalog by name, an exact match wins over a partial one
+pub fn find_store_product(name: &str, catalog: &Catalog) -> Result<Option<Product>> {
+    let mut searchpageprops = SearchPageProps::default(name.to_string());
+    searchpageprops.set_categories(vec![catalog.clone()]);
+    let productcatalog = get_search_product_catalog(&searchpageprops)?;
+    let lowercase_name = name.to_lowercase();
+    let candidates: Vec<Product> = productcatalog
+        .data
+        .into_iter()
+        .filter(|p| Catalog::id_to_catalog(&p.typeid.to_string()) == *catalog)
+        .filter(|p| !p.downloaddetails.is_empty())
+        .collect();
+    let product = match candidates
+        .iter()
+        .position(|p| p.name.to_lowercase() == lowercase_name)
+    {
+        Some(index) => candidates.into_iter().nth(index),
+        None => candidates
+            .into_iter()
+            .find(|p| p.name.to_lowercase().contains(&lowercase_name)),
+    };
+    Ok(product)
+}
+
+// Deletes the directories an install created and drops it from the manifest
+pub fn uninstall_theme(record: &InstallRecord) -> Result<()> {
+    let location = PathBuf::from(&record.location);
+    let backup_dir = location.join(BACKUP_DIR_NAME);
+    // Backups of what this install replaced go too, nothing is left to roll back to
+    let theme_dirs: Vec<PathBuf> = record
+        .directories
+        .iter()
+        .filter(|d| is_plain_directory_name(d))
+        .map(|d| location.join(d))
+        .chain(
+            record
+                .backups
+                .iter()
+                .filter(|d| is_plain_directory_name(d))
+                .map(|d| backup_dir.join(d)),
+        )
+        .filter(|d| d.exists())
+        .collect();
+    if record.scope.is_elevated() && !theme_dirs.is_empty() {
+        let output = Command::new("pkexec")
+            .arg("rm")
+            .arg("-rf")
+            .arg("--")
+            .args(&theme_dirs)
+            .output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to remove {} : {}",
+                record.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+    } else {
+        for theme_dir in &theme_dirs {
+            fs::remove_dir_all(theme_dir)?;
+        }
+    }
+    InstallManifest::update(|manifest| manifest.remove(record))
+}
+
+// Full command line used to unpack `path` into `extract_path`
+fn extract_command_line(path: &str, extract_path: &Path) -> Option<Vec<String>> {
+    let extract_path = extract_path.display().to_string();
+    let sevenzip_output = format!("-o{}", extract_path);
+    let command_line = match ArchiveKind::from_path(path)? {
+        ArchiveKind::Tar => vec!["tar", "-xf", path, "-C", &extract_path],
+        ArchiveKind::SevenZip => vec!["7z", "x", "-y", path, &sevenzip_output],
+        ArchiveKind::Zip => vec!["unzip", "-o", path, "-d", &extract_path],
+    };
+    Some(command_line.into_iter().map(String::from).collect())
+}
+
+// Top level directories an archive will create, read from its listing
+pub fn list_archive_directories(path: &str) -> Result<Vec<String>> {
+    let kind = match ArchiveKind::from_path(path) {
+        Some(kind) => kind,
+        None => return Ok(vec![]),
+    };
+    let output = match kind {
+        ArchiveKind::Tar => Command::new("tar").arg("-tf").arg(path).output()?,
+        ArchiveKind::SevenZip => Command::new("7z")
+            .arg("l")
+            .arg("-ba")
+            .arg("-slt")
+            .arg(path)
+            .output()?,
+        ArchiveKind::Zip => Command::new("unzip").arg("-Z1").arg(path).output()?,
+    };
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to list {} : {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let mut directories: Vec<String> = vec![];
+    for line in listing.lines() {
+        let entry = match kind {
+            ArchiveKind::SevenZip => match line.strip_prefix("Path = ") {
+                Some(entry) => entry,
+                None => continue,
+            },
+            _ => line,
+        };
+        let entry = entry.trim_start_matches("./");
+        // Only entries with a path separator live inside a directory, loose files are skipped
+        if let Some((top, _)) = entry.split_once('/')
+            && !top.is_empty()
+            && top != "."
+            && !directories.iter().any(|d| d == top)
+        {
+            directories.push(top.to_string());
+        }
+    }
+    Ok(directories)
+}
+
+pub fn install_tar(path: &str, extract_path: &Path) -> Result<Vec<String>> {
+    let command_line = match extract_command_line(path, extract_path) {
+        Some(command_line) => command_line,
+        None => return Err(format!("Unsupported file type: {}", path).into()),
+    };
+    let directories = list_archive_directories(path)?;
+
+    fs::create_dir_all(extract_path)?;
+    let mut command = Command::new(&command_line[0]);
+    command.args(&command_line[1..]);
+
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to extract {} : {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(directories)
+}
@@ -1,15 +1,47 @@
+mod archive_cache;
+mod desktop;
+mod details;
+mod favorites;
+mod filters;
+mod follows;
+mod gallery;
+mod gtk4_config;
+mod index_theme;
+mod installer;
+mod manifest;
+mod markup;
+mod profiles;
+mod queue;
+mod settings;
+mod storage;
+mod sync;
+mod theme_preview;
+mod validator;
+mod variants;
+mod xcursor;
+
 use adw::glib::object::IsA;
+use adw::glib::prelude::{Cast, ObjectExt, ToVariant};
 use adw::gtk::DrawingArea;
 use adw::gtk::SearchEntry;
-use adw::prelude::{ActionRowExt, AdwDialogExt, ExpanderRowExt, PreferencesGroupExt};
+use adw::prelude::{
+    ActionRowExt, AdwDialogExt, AlertDialogExt, AlertDialogExtManual, ComboRowExt, EntryRowExt,
+    ExpanderRowExt, PreferencesDialogExt, PreferencesGroupExt, PreferencesPageExt, PreferencesRowExt,
+};
 use chrono::DateTime;
-use gtk4::prelude::{ButtonExt, DrawingAreaExt, DrawingAreaExtManual, EditableExt};
-use gtk4::{Button, ContentFit, CssProvider, GestureClick, Image, License};
+use gtk4::prelude::{
+    AdjustmentExt, ButtonExt, CheckButtonExt, DrawingAreaExt, DrawingAreaExtManual, EditableExt,
+    ToggleButtonExt,
+};
+use gtk4::{
+    Button, CheckButton, ContentFit, CssProvider, DropDown, FileDialog, FileFilter, GestureClick,
+    Image, License, MenuButton, Popover, PositionType, ToggleButton,
+};
 use reqwest::blocking::Client;
 use serde::de::Deserializer;
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
 
 use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
@@ -17,11 +49,12 @@ use std::thread::{self};
 
 use std::fs;
 use std::fs::File;
+use std::path::PathBuf;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
 
-use adw::gio::prelude::{ApplicationExt, ApplicationExtManual};
-use adw::gtk::prelude::{BoxExt, GtkWindowExt, WidgetExt};
+use adw::gio::prelude::{ActionMapExt, ApplicationExt, ApplicationExtManual, FileExt};
+use adw::gtk::prelude::{BoxExt, GtkApplicationExt, GtkWindowExt, WidgetExt};
 use adw::gtk::{
     glib, Align, Box as GtkBox, FlowBox, Label, ListBox, Orientation, Picture, PolicyType,
     ScrolledWindow, SelectionMode,
@@ -32,21 +65,51 @@ use adw::{
 };
 use gtk4::pango::EllipsizeMode;
 
+use details::{fetch_product_page, Comment, Person, ProductPage};
+use favorites::Favorites;
+use filters::{FilteredListing, ListingFilter};
+use follows::Follows;
+use gallery::{build_preview_gallery, download_preview, missing_preview, preview_path};
+use gtk4_config::{apply_gtk4_theme, revert_gtk4_theme, Gtk4ThemeState, LinkMode};
+use installer::{install_missing_parents, InstallOutcome, InstallScope};
+use manifest::{InstallManifest, InstallRecord};
+use markup::{html_to_markup, html_to_text, is_safe_uri};
+use settings::{Settings, APP_ID};
+use storage::format_size;
+use profiles::{apply_profile, install_missing_components, Profile, Profiles};
+use queue::JobState;
+use theme_preview::{prepare_preview, ThemePreview};
+use variants::{
+    dimension_values, matches_selection, parse_variant, recommended_variant, Dimension,
+    VariantTags,
+};
+
 // Libadwwaita Libraries
 
 pub type Error = std::boxed::Box<dyn core::error::Error>;
 pub type Result<T> = core::result::Result<T, Error>;
 
+// Seconds between checks for new uploads of followed authors
+const FOLLOW_CHECK_INTERVAL: u32 = 60 * 60;
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadDetail {
     pub downloadlink: String,
     pub downloadname: String,
     pub downloadsize: u64,
+    #[serde(default)]
+    pub downloadmd5sum: String,
+}
+impl DownloadDetail {
+    // OCS reports download sizes in kilobytes
+    pub fn size_in_bytes(&self) -> u64 {
+        self.downloadsize.saturating_mul(1024)
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct ProductCatalog {
     pub status: String,
     pub statuscode: i64,
@@ -70,6 +133,15 @@ pub struct Product {
     pub score: f32,
     pub downloads: String,
     pub description: String,
+    // Unmodified OCS description, for rendering its formatting
+    pub description_html: String,
+    pub version: String,
+    pub license: String,
+    pub changelog: String,
+    pub homepage: String,
+    pub detailpage: String,
+    // Number of comments
+    pub comments: i64,
     pub previewpics: Vec<String>,
     pub downloaddetails: Vec<DownloadDetail>,
 }
@@ -79,21 +151,9 @@ impl<'de> Deserialize<'de> for Product {
     where
         D: Deserializer<'de>,
     {
-        fn strip_html(source: &str) -> String {
-            let mut inside = false;
-            source.chars()
-                .filter(|&c| {
-                    match c {
-                        '<' => { inside = true; false },
-                        '>' => { inside = false; false },
-                        _ => !inside,
-                    }
-                })
-                .collect()
-        }
-
         fn split_field(key: &str) -> Option<(&str, usize)> {
-            let digits_start = key.chars().position(|c| c.is_ascii_digit())?;
+            // Trailing digits only, "downloadmd5sum1" is field "downloadmd5sum" number 1
+            let digits_start = key.trim_end_matches(|c: char| c.is_ascii_digit()).len();
             let (field, number) = key.split_at(digits_start);
             number.parse().ok().map(|n| (field, n))
         }
@@ -102,7 +162,6 @@ impl<'de> Deserialize<'de> for Product {
             details: String,
             id: i64,
             name: String,
-            //version: String,
             typeid: i64,
             typename: String,
             personid: String,
@@ -117,14 +176,36 @@ impl<'de> Deserialize<'de> for Product {
         }
 
         let helper = ProductHelper::deserialize(deserializer)?;
-        let mut previewpics = vec![];
-
-        for i in 1..=10 {
-            let key = format!("previewpic{}", i);
-            if let Some(serde_json::Value::String(url)) = helper.extra.get(&key) {
-                previewpics.push(url.clone());
-            }
-        }
+        // Optional fields come as strings, numbers or null depending on the product
+        let extra_string = |key: &str| match helper.extra.get(key) {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(serde_json::Value::Number(value)) => value.to_string(),
+            _ => String::new(),
+        };
+        let version = extra_string("version");
+        let license = match extra_string("license") {
+            license if license.is_empty() => extra_string("licensetype"),
+            license => license,
+        };
+        let changelog = extra_string("changelog");
+        let homepage = extra_string("homepage");
+        let detailpage = extra_string("detailpage");
+        let comments = extra_string("comments").parse().unwrap_or(0);
+        // previewpic1, previewpic2, ... as many as the product has, in order
+        let mut numbered_previews: Vec<(usize, String)> = helper
+            .extra
+            .iter()
+            .filter_map(|(key, value)| match (split_field(key), value) {
+                (Some(("previewpic", number)), serde_json::Value::String(url))
+                    if !url.is_empty() =>
+                {
+                    Some((number, url.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        numbered_previews.sort();
+        let previewpics: Vec<String> = numbered_previews.into_iter().map(|(_, url)| url).collect();
 
         // Parse numbered download entries into DownloadDetail
         let mut download_map: HashMap<u32, DownloadDetail> = HashMap::new();
@@ -140,7 +221,7 @@ impl<'de> Deserialize<'de> for Product {
                     downloadlink: String::new(),
                     downloadname: String::new(),
                     downloadsize: 0,
-                    //downloadmd5sum: String::new(),
+                    downloadmd5sum: String::new(),
                 });
                 match field {
                     "downloadlink" => {
@@ -150,7 +231,9 @@ impl<'de> Deserialize<'de> for Product {
                         entry.downloadname = value.as_str().unwrap_or_default().to_string()
                     }
                     "downloadsize" => entry.downloadsize = value.as_u64().unwrap_or(0),
-                    //"downloadmd5sum" => entry.downloadmd5sum = value.as_str().unwrap_or_default().to_string(),
+                    "downloadmd5sum" => {
+                        entry.downloadmd5sum = value.as_str().unwrap_or_default().to_string()
+                    }
                     _ => {}
                 }
             }
@@ -168,7 +251,6 @@ impl<'de> Deserialize<'de> for Product {
             details: helper.details,
             id: helper.id,
             name: helper.name,
-            //            version: helper.version,
             typeid: helper.typeid,
             typename: helper.typename,
             changed: helper.changed,
@@ -179,7 +261,14 @@ impl<'de> Deserialize<'de> for Product {
                 true => "0".to_string(),
                 false => helper.downloads,
             },
-            description: strip_html(&helper.description),
+            description: html_to_text(&helper.description),
+            description_html: helper.description,
+            version,
+            license,
+            changelog,
+            homepage,
+            detailpage,
+            comments,
             previewpics,
             downloaddetails,
         })
@@ -281,12 +370,36 @@ impl Catalog {
         ]
     }
 }
+// Stores themes can be installed from, only pling.com for now
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    Pling,
+}
+impl Provider {
+    pub fn to_string(&self) -> &str {
+        match &self {
+            Provider::Pling => "Pling",
+        }
+    }
+    pub fn get_base_url(&self) -> &str {
+        match &self {
+            Provider::Pling => "www.pling.com",
+        }
+    }
+    pub fn get_all_providers() -> Vec<Provider> {
+        vec![Provider::Pling]
+    }
+}
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProductPageProps {
     pub pageno: u16,
     pub sortby: SortType,
     pub cat: Catalog,
     pub pagesize: u8,
+    // Lists this author's products from every catalog instead of one catalog
+    pub user: Option<String>,
 }
 
 impl Default for ProductPageProps {
@@ -297,6 +410,7 @@ impl Default for ProductPageProps {
             sortby: SortType::Latest,
             cat: Catalog::Gtk4Themes,
             pagesize: 10,
+            user: None,
         }
     }
 }
@@ -314,9 +428,17 @@ impl ProductPageProps {
         self.sortby = sortby;
         self
     }
+    pub fn set_pagesize(&mut self, pagesize: u8) -> &mut ProductPageProps {
+        self.pagesize = pagesize;
+        self
+    }
+    pub fn set_user(&mut self, user: Option<String>) -> &mut ProductPageProps {
+        self.user = user;
+        self
+    }
     pub fn get_url(&self) -> String {
         //let base_url: Result<String> = get_env_val("BASE_URL");
-        let base_url = String::from("www.pling.com");
+        let base_url = Settings::load().provider.get_base_url().to_string();
         /*println!("URL : {}", String::from("https://")
         + &base_url
         + "/ocs/v1/content/data?format=json&pagesize="
@@ -327,16 +449,30 @@ impl ProductPageProps {
         + format!("{}", self.pageno).as_str()
         + "&sortmode="
         + self.sortby.get_label());*/
-        String::from("https://")
+        let categories = match &self.user {
+            Some(_) => Catalog::get_all_catalog_types()
+                .iter()
+                .map(|c| c.get_id())
+                .collect::<Vec<&str>>()
+                .join(","),
+            None => self.cat.get_id().to_string(),
+        };
+        let url = String::from("https://")
             + &base_url
             + "/ocs/v1/content/data?format=json&pagesize="
             + format!("{}", self.pagesize).as_str()
             + "&categories="
-            + self.cat.get_id()
+            + &categories
             + "&page="
             + format!("{}", self.pageno).as_str()
             + "&sortmode="
-            + self.sortby.get_label()
+            + self.sortby.get_label();
+        match &self.user {
+            Some(user) => reqwest::Url::parse_with_params(&url, &[("user", user.as_str())])
+                .map(|url| url.to_string())
+                .unwrap_or(url),
+            None => url,
+        }
     }
 }
 
@@ -345,6 +481,9 @@ impl ProductPageProps {
 pub struct SearchPageProps {
     pub query: String,
     pub pagesize: u8,
+    pub pageno: u16,
+    pub sortby: SortType,
+    pub categories: Vec<Catalog>,
 }
 
 impl SearchPageProps {
@@ -353,6 +492,9 @@ impl SearchPageProps {
         SearchPageProps {
             query: search_text,
             pagesize: 30,
+            pageno: 0,
+            sortby: SortType::Latest,
+            categories: Catalog::get_all_catalog_types(),
         }
     }
 
@@ -360,17 +502,41 @@ impl SearchPageProps {
         self.query = query;
         self
     }
+    pub fn set_page(&mut self, pageno: u16) -> &mut SearchPageProps {
+        self.pageno = pageno;
+        self
+    }
+    pub fn set_order(&mut self, sortby: SortType) -> &mut SearchPageProps {
+        self.sortby = sortby;
+        self
+    }
+    pub fn set_categories(&mut self, categories: Vec<Catalog>) -> &mut SearchPageProps {
+        self.categories = categories;
+        self
+    }
     pub fn get_search_url(&self) -> String {
         //let base_url: Result<String> = get_env_val("BASE_URL");
-        let base_url = String::from("www.pling.com");
-        String::from("https://")
-            + &base_url
-            + "/ocs/v1/content/data?format=json&categories=132,107,134,135,104&pagesize="
-            + format!("{}", self.pagesize).as_str()
-            + "&page=0"
-            + "&sortmode=update"
-            + "&search="
-            + self.query.as_str()
+        let base_url = Settings::load().provider.get_base_url().to_string();
+        // An empty selection searches everything rather than nothing
+        let categories = match self.categories.is_empty() {
+            true => Catalog::get_all_catalog_types(),
+            false => self.categories.clone(),
+        };
+        let categories: Vec<&str> = categories.iter().map(|c| c.get_id()).collect();
+        let url = String::from("https://") + &base_url + "/ocs/v1/content/data";
+        reqwest::Url::parse_with_params(
+            &url,
+            &[
+                ("format", "json"),
+                ("categories", categories.join(",").as_str()),
+                ("pagesize", self.pagesize.to_string().as_str()),
+                ("page", self.pageno.to_string().as_str()),
+                ("sortmode", self.sortby.get_label()),
+                ("search", self.query.as_str()),
+            ],
+        )
+        .map(|url| url.to_string())
+        .unwrap_or(url)
     }
 }
 
@@ -386,7 +552,7 @@ pub fn get_formatted_date(dt: &str) -> String {
     }
 }
 
-fn fetch_url(url: &String, file_name: String) -> Result<()> {
+fn _fetch_url(url: &String, file_name: String) -> Result<()> {
     let response = reqwest::blocking::get(url);
     match response {
         Ok(val) => match val.bytes() {
@@ -414,79 +580,6 @@ fn fetch_url(url: &String, file_name: String) -> Result<()> {
 
     Ok(())
 }
-fn install_theme(downloaddetail: &DownloadDetail, themetype: &Catalog) -> Result<()> {
-    let mut path = String::from("/tmp/themedownloadfiles/");
-    path.push_str(themetype.to_string());
-    path.push_str("/");
-
-    let _ = fs::create_dir_all(&path.as_str());
-    path.push_str(&downloaddetail.downloadname);
-    match std::path::Path::new(&path).exists() {
-        true => {
-        }
-        false => {
-            let _res = fetch_url(&downloaddetail.downloadlink, path.clone());
-        }
-    }
-    let _ = install_tar(
-        &path.clone(),
-        &themetype,
-    )
-    .unwrap();
-    Ok(())
-}
-
-use std::path::PathBuf;
-use std::process::Command;
-
-fn install_tar(path: &str, theme_type: &Catalog) -> Result<()> {
-    // Construct the target extraction path
-    let home_dir = std::env::var("HOME")?;
-    let mut extract_path = PathBuf::from(home_dir);
-
-    match theme_type {
-        Catalog::FullIconThemes | Catalog::Cursors => {
-            extract_path.push(".local/share/icons");
-        }
-        Catalog::Gtk4Themes | Catalog::GnomeShellThemes => {
-            extract_path.push(".local/share/themes");
-        }
-        Catalog::KDEThemes => {
-            extract_path.push(".local/share/plasma/desktoptheme");
-        }
-    }
-
-    fs::create_dir_all(&extract_path)?;
-
-    if path.ends_with(".tar") || path.ends_with(".tar.xz") || path.ends_with(".tar.gz") {
-        Command::new("tar")
-            .arg("-xf")
-            .arg(&path)
-            .arg("-C")
-            .arg(&extract_path)
-            .output()
-            .expect("Failed to extract .tar/.tar.xz/.tar.gz");
-    } else if path.ends_with(".7z") {
-        Command::new("7z")
-            .arg("x")
-            .arg(&path)
-            .arg(format!("-o{}", extract_path.display()))
-            .output()
-            .expect("Failed to extract .7z");
-    } else if path.ends_with(".zip") {
-        Command::new("unzip")
-            .arg(&path)
-            .arg("-d")
-            .arg(&extract_path)
-            .output()
-            .expect("Failed to extract .zip");
-    } else {
-        println!("Unsupported file type: {}", path);
-    }
-
-    Ok(())
-}
-
 pub struct CircleRating {
     area: DrawingArea,
     rating: Rc<RefCell<f64>>, // 0.0 to 5.0
@@ -577,6 +670,9 @@ pub fn load_custom_css() {
                     .card {
                         box-shadow: 0 8px 8px 0 rgba(0, 0, 0, 0.2), 0 6px 8px 0 rgba(0, 0, 0, 0.1);
                     }
+                    .favorite:checked {
+                        color: @error_color;
+                    }
                     button:hover {
                         transform: scale(1.01); /* 120% zoom */
                     }
@@ -612,74 +708,36 @@ pub fn get_product_catalog(prodpageprops: &ProductPageProps) -> Result<ProductCa
 }
 
 pub fn get_search_product_catalog(searchpageprops: &SearchPageProps) -> Result<ProductCatalog> {
-    //let mut buffer = Vec::<u8>::new();
-    let client = Client::new();
     let url = searchpageprops.get_search_url();
-    let url = url.as_str();
-    println!("Search Url : {}", url);
-    let res: serde_json::Value = client
-        .get(url)
-        .send()
-        .expect(format!("Invalid Url : {}", url).as_str())
-        .json()
-        .expect("Failed to get payload");
-    //println!("{}", serde_json::to_string_pretty(&res).unwrap());
-
-    let resp_json_products: ProductCatalog = serde_json::from_value(res).unwrap();
-    println!(
-        "{}",
-        serde_json::to_string_pretty(&resp_json_products).unwrap()
-    );
+    let res: serde_json::Value = Client::new()
+        .get(&url)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    let resp_json_products: ProductCatalog = serde_json::from_value(res)?;
     Ok(resp_json_products)
 }
 
-fn downloadthumb(each_product: &Product) -> Result<()> {
-    //println!("Got inside Download Thumbnail");
-
-    let firstimage = match each_product.previewpics.len() {
-        0 => None,
-        _ => Some(&each_product.previewpics[0]),
-    };
-    if firstimage.is_none() {
-        return Ok(());
-    }
-    let firstimage = firstimage.unwrap();
-    let save_path = "/tmp/themeinstaller/cache/".to_string() + &firstimage;
-    if !std::path::Path::new(&save_path).exists() {
-        let mut save_dir = save_path.to_string();
-        save_dir.push_str(&firstimage);
-
-        let save_dir = &save_dir[0..save_dir.rfind('/').unwrap()];
-        let save_dir_copy = save_dir;
-        let _ = fs::create_dir_all(&save_dir_copy);
-
-        fetch_url(&firstimage.replace("770x540", "770x540"), save_path).unwrap();
+pub fn get_product(productid: i64) -> Result<Product> {
+    let base_url = Settings::load().provider.get_base_url().to_string();
+    let url = String::from("https://")
+        + &base_url
+        + "/ocs/v1/content/data/"
+        + productid.to_string().as_str()
+        + "?format=json";
+    let res: serde_json::Value = Client::new().get(&url).send()?.error_for_status()?.json()?;
+    let productcatalog: ProductCatalog = serde_json::from_value(res)?;
+    match productcatalog.data.into_iter().next() {
+        Some(product) => Ok(product),
+        None => Err(format!("Product {} not found", productid).into()),
     }
-
-    Ok(())
-
-    //);
 }
 
-fn downloadotherimages(each_product: &Product) -> Result<()> {
-    //println!("Got inside Download Thumbnail");
-
-    for each_image in &each_product.previewpics[1..] {
-        let save_path = "/tmp/themeinstaller/cache/".to_string() + &each_image;
-        if !std::path::Path::new(&save_path).exists() {
-            let mut save_dir = save_path.to_string();
-            save_dir.push_str(&each_image);
-
-            let save_dir = &save_dir[0..save_dir.rfind('/').unwrap()];
-            let save_dir_copy = save_dir;
-            let _ = fs::create_dir_all(&save_dir_copy);
-
-            fetch_url(&each_image.replace("770x540", "770x540"), save_path).unwrap();
-        }
+fn downloadthumb(each_product: &Product) -> Result<()> {
+    if let Some(firstimage) = each_product.previewpics.first() {
+        download_preview(firstimage)?;
     }
     Ok(())
-
-    //);
 }
 
 fn _downloadthumbs(products: Vec<Product>) -> Result<()> {
@@ -709,7 +767,7 @@ fn _downloadthumbs(products: Vec<Product>) -> Result<()> {
                     let save_dir_copy = save_dir;
                     let _ = fs::create_dir_all(&save_dir_copy);
 
-                    fetch_url(&image_small.replace("770x540", "770x540"), save_path).unwrap();
+                    _fetch_url(&image_small.replace("770x540", "770x540"), save_path).unwrap();
                 }
             }
         });
@@ -748,7 +806,14 @@ fn build_category_page(
 
     themecategorysortbybutton.set_valign(Align::Start);
     themecategorysortbybutton.set_halign(Align::Center);
-    themecategoryloadingpage.append(&themecategorysortbybutton);
+
+    // Sort switcher with the filter button next to it
+    let filteredlisting = FilteredListing::new(theme_type);
+    let themecategorysortbar = GtkBox::new(Orientation::Horizontal, 10);
+    themecategorysortbar.set_halign(Align::Center);
+    themecategorysortbar.append(&themecategorysortbybutton);
+    themecategorysortbar.append(&build_filter_button(&filteredlisting));
+    themecategoryloadingpage.append(&themecategorysortbar);
     //outer_view_stack.append(&fulliconsortbybutton);
 
     outer_view_stack.append(&themecategorysortbybutton);
@@ -763,15 +828,136 @@ fn build_category_page(
     // Starting async loading of items for each page
     // fullcionprodpage
 
+    let settings = Settings::load();
     for each_sorting_type in SortType::get_all_sort_types() {
         build_content_box(
             ProductPageProps::default()
                 .set_catalog(theme_type.to_owned())
-                .set_order(each_sorting_type.to_owned()),
+                .set_order(each_sorting_type.to_owned())
+                .set_pagesize(settings.page_size),
             &themecategorysortby_view_stack,
             &window,
+            &filteredlisting,
         );
     }
+    themecategorysortby_view_stack.set_visible_child_name(settings.default_sort.to_string());
+}
+
+// Filter popover for a catalog page, changes apply to every loaded page at once
+fn build_filter_button(filteredlisting: &FilteredListing) -> MenuButton {
+    let filter = filteredlisting.filter();
+    let filterbutton = MenuButton::builder()
+        .label("Filters")
+        .valign(Align::Center)
+        .build();
+
+    let scorerow = adw::SpinRow::with_range(0.0, 10.0, 0.5);
+    scorerow.set_title("Minimum Score");
+    scorerow.set_digits(1);
+    scorerow.set_value(filter.min_score.into());
+    let downloadsrow = adw::SpinRow::with_range(0.0, 10_000_000.0, 100.0);
+    downloadsrow.set_title("Minimum Downloads");
+    downloadsrow.set_value(filter.min_downloads as f64);
+    let updatedrow = adw::SpinRow::with_range(0.0, 120.0, 1.0);
+    updatedrow.set_title("Updated Within Months");
+    updatedrow.set_subtitle("0 shows themes of any age");
+    updatedrow.set_value(filter.updated_within_months.into());
+    let archiverow = adw::SwitchRow::builder()
+        .title("Has .tar.xz or .zip Variant")
+        .active(filter.archive_variant)
+        .build();
+    let previewsrow = adw::SwitchRow::builder()
+        .title("Has Previews")
+        .active(filter.has_previews)
+        .build();
+
+    let filterlist = ListBox::builder()
+        .selection_mode(SelectionMode::None)
+        .css_classes(vec![String::from("boxed-list")])
+        .width_request(360)
+        .build();
+    filterlist.append(&scorerow);
+    filterlist.append(&downloadsrow);
+    filterlist.append(&updatedrow);
+    filterlist.append(&archiverow);
+    filterlist.append(&previewsrow);
+
+    let countlabel = Label::builder()
+        .css_classes(vec!["caption", "dimmed"])
+        .build();
+    let resetbutton = Button::builder()
+        .label("Reset")
+        .halign(Align::End)
+        .css_classes(vec!["flat"])
+        .build();
+    let filterfooter = GtkBox::new(Orientation::Horizontal, 10);
+    countlabel.set_hexpand(true);
+    countlabel.set_halign(Align::Start);
+    filterfooter.append(&countlabel);
+    filterfooter.append(&resetbutton);
+
+    let filterbox = GtkBox::new(Orientation::Vertical, 10);
+    filterbox.set_margin_top(6);
+    filterbox.set_margin_bottom(6);
+    filterbox.set_margin_start(6);
+    filterbox.set_margin_end(6);
+    filterbox.append(&filterlist);
+    filterbox.append(&filterfooter);
+    let filterpopover = Popover::builder().child(&filterbox).build();
+    filterbutton.set_popover(Some(&filterpopover));
+
+    let update_label = {
+        let filteredlisting = filteredlisting.clone();
+        let filterbutton = filterbutton.clone();
+        let countlabel = countlabel.clone();
+        move || {
+            let (matching, loaded) = filteredlisting.counts();
+            countlabel.set_label(&format!("{} of {} loaded themes match", matching, loaded));
+            match filteredlisting.filter().is_active() {
+                true => filterbutton.set_label("Filters (On)"),
+                false => filterbutton.set_label("Filters"),
+            }
+        }
+    };
+    update_label();
+    let popover_update_label = update_label.clone();
+    filterpopover.connect_show(move |_| popover_update_label());
+
+    let apply_filter = {
+        let filteredlisting = filteredlisting.clone();
+        let scorerow = scorerow.clone();
+        let downloadsrow = downloadsrow.clone();
+        let updatedrow = updatedrow.clone();
+        let archiverow = archiverow.clone();
+        let previewsrow = previewsrow.clone();
+        move || {
+            filteredlisting.set_filter(ListingFilter {
+                min_score: scorerow.value() as f32,
+                min_downloads: downloadsrow.value() as u64,
+                updated_within_months: updatedrow.value() as u32,
+                archive_variant: archiverow.is_active(),
+                has_previews: previewsrow.is_active(),
+            });
+            update_label();
+        }
+    };
+    for spinrow in [&scorerow, &downloadsrow, &updatedrow] {
+        let apply_filter = apply_filter.clone();
+        spinrow.connect_value_notify(move |_| apply_filter());
+    }
+    for switchrow in [&archiverow, &previewsrow] {
+        let apply_filter = apply_filter.clone();
+        switchrow.connect_active_notify(move |_| apply_filter());
+    }
+    resetbutton.connect_clicked(move |_| {
+        scorerow.set_value(0.0);
+        downloadsrow.set_value(0.0);
+        updatedrow.set_value(0.0);
+        archiverow.set_active(false);
+        previewsrow.set_active(false);
+    });
+
+    filterbutton
 }
 
 fn build_search_page(
@@ -785,7 +971,7 @@ fn build_search_page(
         view_stack.add_titled(&searchbox, Some("Search Themes"), "Search Themes");
 
     let searchinput = SearchEntry::new();
-    searchinput.set_search_delay(500);
+    searchinput.set_search_delay(Settings::load().search_delay);
     searchinput.set_placeholder_text(Some("e.g. Papirus Theme"));
 
     //searchinput.add_css_class("round");
@@ -802,6 +988,29 @@ fn build_search_page(
     searchinputbox.append(&searchinput);
     searchbox.append(&searchinputbox);
 
+    // Category filters and sort order for the search
+    let searchfilterbox = GtkBox::new(Orientation::Horizontal, 6);
+    searchfilterbox.set_halign(Align::Center);
+    let categorytoggles: Vec<(Catalog, ToggleButton)> = Catalog::get_all_catalog_types()
+        .into_iter()
+        .map(|catalog| {
+            let toggle = ToggleButton::builder()
+                .label(catalog.to_string())
+                .active(true)
+                .build();
+            searchfilterbox.append(&toggle);
+            (catalog, toggle)
+        })
+        .collect();
+    let sortlabels: Vec<&str> = SortType::get_all_sort_types()
+        .iter()
+        .map(|sorttype| sorttype.to_string())
+        .collect();
+    let sortdropdown = DropDown::from_strings(&sortlabels);
+    sortdropdown.set_valign(Align::Center);
+    searchfilterbox.append(&sortdropdown);
+    searchinputbox.append(&searchfilterbox);
+
     outer_view_stack.append(&searchbox);
     let window_clone = window.clone();
 
@@ -812,104 +1021,1171 @@ fn build_search_page(
     searchresultpage.set_vexpand(true);
     searchresultpage.set_hexpand(true);
 
-    //let searchpageprops = SearchPageProps::default("".to_owned());
-    build_search_content_box(&searchinput, &searchresultpage, &window_clone);
+    // Shared by the search entry, the filters and the tab switch handler below,
+    // so the query survives moving between the search page and the catalog tabs
+    let searchpageprops = Rc::new(RefCell::new(SearchPageProps::default(
+        searchinput.text().to_string(),
+    )));
+    let start_search =
+        build_search_content_box(&searchinput, &searchresultpage, &window_clone, &searchpageprops);
 
     searchbox.append(&searchresultpage);
-    println!("{}", searchinput.text().to_string());
 
-    //    let searchpageprops = SearchPageProps::default(searchinput.text().to_string() );
+    // Set while the tab switch handler adjusts toggles, so it can tell them from user clicks
+    let updating_filters = Rc::new(RefCell::new(false));
+    let filters_changed_by_user = Rc::new(RefCell::new(false));
+    let categorytoggles = Rc::new(categorytoggles);
+    for (_, toggle) in categorytoggles.iter() {
+        let categorytoggles = categorytoggles.clone();
+        let searchpageprops = searchpageprops.clone();
+        let start_search = start_search.clone();
+        let updating_filters = updating_filters.clone();
+        let filters_changed_by_user = filters_changed_by_user.clone();
+        toggle.connect_toggled(move |_| {
+            let categories: Vec<Catalog> = categorytoggles
+                .iter()
+                .filter(|(_, toggle)| toggle.is_active())
+                .map(|(catalog, _)| catalog.clone())
+                .collect();
+            searchpageprops.borrow_mut().set_categories(categories);
+            if !*updating_filters.borrow() {
+                *filters_changed_by_user.borrow_mut() = true;
+                start_search();
+            }
+        });
+    }
+    let sort_searchpageprops = searchpageprops.clone();
+    let sort_start_search = start_search.clone();
+    sortdropdown.connect_selected_notify(move |sortdropdown| {
+        let sorttype = SortType::get_all_sort_types()[sortdropdown.selected() as usize].clone();
+        sort_searchpageprops.borrow_mut().set_order(sorttype);
+        sort_start_search();
+    });
+
+    // Coming from a catalog tab narrows the search to that catalog, unless the
+    // user already picked categories themselves
+    let previous_page = Rc::new(RefCell::new(String::new()));
+    view_stack.connect_visible_child_name_notify(move |view_stack| {
+        let current_page = view_stack
+            .visible_child_name()
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        let from_page = previous_page.replace(current_page.clone());
+        if current_page != "Search Themes" || *filters_changed_by_user.borrow() {
+            return;
+        }
+        let from_catalog = match Catalog::get_all_catalog_types()
+            .into_iter()
+            .find(|catalog| catalog.to_string() == from_page)
+        {
+            Some(from_catalog) => from_catalog,
+            None => return,
+        };
+        *updating_filters.borrow_mut() = true;
+        for (catalog, toggle) in categorytoggles.iter() {
+            toggle.set_active(*catalog == from_catalog);
+        }
+        *updating_filters.borrow_mut() = false;
+        if !searchpageprops.borrow().query.is_empty() {
+            start_search();
+        }
+    });
 }
-// contentbox function
-fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &ApplicationWindow) {
-    let imgpath = "/tmp/themeinstaller/cache/".to_string() + &each_product.previewpics[0];
-    let img = Picture::builder()
+// Heart toggle that bookmarks a product without installing it
+fn build_favorite_button(product: &Product) -> ToggleButton {
+    let favoritebutton = ToggleButton::builder()
+        .icon_name("emblem-favorite-symbolic")
+        .tooltip_text("Add to Favorites")
+        .active(Favorites::load().contains(product.id))
         .valign(Align::Center)
-        .hexpand_set(false)
-        .vexpand_set(false)
-        .margin_start(0)
-        .margin_end(0)
-        .margin_top(0)
-        .margin_bottom(0)
-        .css_name("img-cover")
+        .css_classes(vec!["flat", "circular", "favorite"])
         .build();
-    img.add_css_class("img-round");
-    img.set_content_fit(ContentFit::Cover);
-    //img.set_filename(Some(&std::path::Path::new(imgpath.as_str())));
-    img.set_size_request(260, 260);
-    //img.set_can_shrink(true);
-    let imgclamp = Clamp::new();
-    let imagespinner = Spinner::builder()
-        .valign(Align::Center)
-        .halign(Align::Center)
-        .hexpand(true)
-        .vexpand(true)
-        .width_request(32)
-        .height_request(32)
+    let product = product.clone();
+    favoritebutton.connect_toggled(move |favoritebutton| {
+        favorites::set_favorite(&product, favoritebutton.is_active());
+    });
+    favoritebutton
+}
+
+fn build_favorites_page(view_stack: &ViewStack, window: &ApplicationWindow) {
+    let favoritespage = GtkBox::new(Orientation::Vertical, 10);
+    favoritespage.add_css_class("background");
+    let _favoritespage_viewstack =
+        view_stack.add_titled(&favoritespage, Some("Favorites"), "Favorites");
+
+    let favoritestoolbar = GtkBox::new(Orientation::Horizontal, 10);
+    favoritestoolbar.set_halign(Align::Center);
+    let favoritesstatus = Label::builder()
+        .css_classes(vec!["dimmed"])
         .build();
-    let imagebox = GtkBox::builder()
-        .valign(Align::Center)
-        .halign(Align::Center)
+    let refreshbutton = Button::builder()
+        .icon_name("view-refresh-symbolic")
+        .tooltip_text("Refresh from the Store")
+        .build();
+    let exportbutton = Button::builder()
+        .label("Export")
+        .tooltip_text("Save the favorites list to share it")
+        .build();
+    let importbutton = Button::builder()
+        .label("Import")
+        .tooltip_text("Add favorites from a shared list")
+        .build();
+    favoritestoolbar.append(&favoritesstatus);
+    favoritestoolbar.append(&refreshbutton);
+    favoritestoolbar.append(&exportbutton);
+    favoritestoolbar.append(&importbutton);
+    favoritespage.append(&favoritestoolbar);
+
+    let flowbox = FlowBox::builder().build();
+    flowbox.set_vexpand(true);
+    flowbox.set_hexpand(true);
+    flowbox.set_valign(Align::Start);
+    flowbox.set_halign(Align::Center);
+    flowbox.set_activate_on_single_click(false);
+    flowbox.set_min_children_per_line(1);
+    flowbox.set_max_children_per_line(5);
+    flowbox.set_selection_mode(SelectionMode::None);
+    let scrollwindow = ScrolledWindow::builder()
         .hexpand(true)
         .vexpand(true)
-        .height_request(260)
-        .width_request(260)
+        .child(&flowbox)
         .build();
-    imagebox.append(&imagespinner);
-    //imgclamp.set_child(Some(&img));
-    //println!("Setting SPinner");
-    imgclamp.set_child(Some(&imagebox));
-    imgclamp.set_tightening_threshold(256);
-    imgclamp.set_maximum_size(256);
+    scrollwindow.set_policy(PolicyType::Automatic, PolicyType::Automatic);
+    favoritespage.append(&scrollwindow);
+
+    // Refreshing again before the previous one finished drops its late results
+    let (sender, receiver) =
+        async_channel::unbounded::<(u32, std::result::Result<Product, String>)>();
+    let generation = Rc::new(RefCell::new(0u32));
+    let counts = Rc::new(RefCell::new((0usize, 0usize)));
+    let refresh: Rc<dyn Fn()> = Rc::new({
+        let generation = generation.clone();
+        let counts = counts.clone();
+        let flowbox = flowbox.clone();
+        let favoritesstatus = favoritesstatus.clone();
+        move || {
+            *generation.borrow_mut() += 1;
+            while let Some(child) = flowbox.first_child() {
+                flowbox.remove(&child);
+            }
+            let favorites = Favorites::load().favorites;
+            *counts.borrow_mut() = (0, 0);
+            favoritesstatus.set_label(&match favorites.is_empty() {
+                true => String::from("No favorites yet, use the heart on any theme"),
+                false => format!("Loading {} favorites...", favorites.len()),
+            });
+            let sender = sender.clone();
+            let generation = *generation.borrow();
+            adw::gio::spawn_blocking(move || {
+                for favorite in favorites {
+                    let product = get_product(favorite.productid)
+                        .map_err(|e| format!("{} : {}", favorite.name, e));
+                    sender.send_blocking((generation, product)).unwrap_or_default();
+                }
+            });
+        }
+    });
 
-    let flowboxchild_button = Button::builder()
-        .width_request(256)
-        .css_classes(vec!["flat"])
-        .build();
-    let flowboxchild = GtkBox::builder()
-        .hexpand_set(true)
-        .vexpand_set(true)
-        .orientation(Orientation::Vertical)
-        .valign(Align::End)
-        .halign(Align::End)
-        .margin_start(10)
-        .margin_end(10)
-        .margin_top(5)
-        .margin_bottom(10)
-        .css_classes(vec!["card", "activable1"])
-        .build();
-    flowboxchild_button.set_child(Some(&flowboxchild));
+    let window_clone = window.clone();
+    let receiver_flowbox = flowbox.clone();
+    let receiver_status = favoritesstatus.clone();
+    glib::spawn_future_local(async move {
+        while let Ok((message_generation, product)) = receiver.recv().await {
+            if message_generation != *generation.borrow() {
+                continue;
+            }
+            match product {
+                Ok(product) => {
+                    counts.borrow_mut().0 += 1;
+                    build_flowbox_for_page(&product, &receiver_flowbox, &window_clone);
+                }
+                Err(e) => {
+                    counts.borrow_mut().1 += 1;
+                    eprintln!("Failed to refresh favorite {}", e);
+                }
+            }
+            let (loaded, failed) = *counts.borrow();
+            receiver_status.set_label(&match failed {
+                0 => format!("{} favorites", loaded),
+                _ => format!("{} favorites, {} could not be loaded", loaded, failed),
+            });
+        }
+    });
 
-    let productclamp = Clamp::builder().build();
-    productclamp.set_valign(Align::Start);
-    //productclamp.set_widget_name(&each_product.id.to_string().as_str());
-    productclamp.set_maximum_size(256);
-    productclamp.set_child(Some(&flowboxchild_button));
-    flowboxchild.append(&imgclamp);
+    let visible_refresh = refresh.clone();
+    view_stack.connect_visible_child_name_notify(move |view_stack| {
+        if view_stack.visible_child_name().as_deref() == Some("Favorites") {
+            visible_refresh();
+        }
+    });
+    let button_refresh = refresh.clone();
+    refreshbutton.connect_clicked(move |_| button_refresh());
 
-    let prodnametype_holder = GtkBox::new(Orientation::Vertical, 0);
-    prodnametype_holder.set_margin_bottom(10);
-    prodnametype_holder.set_margin_top(0);
-    prodnametype_holder.set_margin_start(0);
-    prodnametype_holder.set_margin_end(10);
-    prodnametype_holder.set_valign(Align::End);
-    prodnametype_holder.set_halign(Align::End);
+    let window_clone = window.clone();
+    let export_status = favoritesstatus.clone();
+    exportbutton.connect_clicked(move |_| {
+        let filedialog = FileDialog::builder()
+            .title("Export Favorites")
+            .initial_name("favorites.json")
+            .build();
+        let export_status = export_status.clone();
+        filedialog.save(Some(&window_clone), None::<&adw::gio::Cancellable>, move |file| {
+            let path = match file.ok().and_then(|file| file.path()) {
+                Some(path) => path,
+                None => return,
+            };
+            let favorites = Favorites::load();
+            export_status.set_label(&match favorites.export(&path) {
+                Ok(_) => format!(
+                    "Exported {} favorites to {}",
+                    favorites.favorites.len(),
+                    path.display()
+                ),
+                Err(e) => format!("Export failed : {}", e),
+            });
+        });
+    });
 
-    prodnametype_holder.append(
-        &Label::builder()
-            .label(&each_product.name)
-            .ellipsize(EllipsizeMode::End)
-            .margin_bottom(0)
-            .margin_top(5)
-            .margin_start(10)
+    let window_clone = window.clone();
+    importbutton.connect_clicked(move |_| {
+        let jsonfilter = FileFilter::new();
+        jsonfilter.set_name(Some("Favorites List"));
+        jsonfilter.add_suffix("json");
+        let filedialog = FileDialog::builder()
+            .title("Import Favorites")
+            .default_filter(&jsonfilter)
+            .build();
+        let refresh = refresh.clone();
+        let import_status = favoritesstatus.clone();
+        filedialog.open(Some(&window_clone), None::<&adw::gio::Cancellable>, move |file| {
+            let path = match file.ok().and_then(|file| file.path()) {
+                Some(path) => path,
+                None => return,
+            };
+            let mut favorites = Favorites::load();
+            let message = match favorites.import(&path) {
+                Ok(added) => match favorites.save() {
+                    Ok(_) => {
+                        refresh();
+                        format!("Imported {} new favorites", added)
+                    }
+                    Err(e) => format!("Import failed : {}", e),
+                },
+                Err(e) => format!("Import failed : {}", e),
+            };
+            import_status.set_label(&message);
+        });
+    });
+}
+
+// All products of one author with the same sort tabs and cards as a catalog page
+fn show_author_page(personid: &str, window: &ApplicationWindow) {
+    let dialog = adw::PreferencesDialog::builder()
+        .can_close(true)
+        .presentation_mode(adw::DialogPresentationMode::Floating)
+        .content_width(1200)
+        .content_height(900)
+        .build();
+
+    let dialogbox = GtkBox::new(Orientation::Vertical, 10);
+    let dialogheader = HeaderBar::builder()
+        .css_classes(vec!["background"])
+        .build();
+    let header_title = adw::WindowTitle::new(personid, "All themes by this author");
+    dialogheader.set_title_widget(Some(&header_title));
+    dialogheader.pack_end(&build_follow_button(personid));
+    dialogbox.append(&dialogheader);
+
+    let authorsortbybutton = adw::InlineViewSwitcher::new();
+    authorsortbybutton.set_can_shrink(true);
+    authorsortbybutton.set_halign(Align::Center);
+    let authorsortby_view_stack = adw::ViewStack::new();
+    authorsortby_view_stack.set_enable_transitions(true);
+    authorsortbybutton.set_stack(Some(&authorsortby_view_stack));
+    dialogbox.append(&authorsortbybutton);
+    dialogbox.append(&authorsortby_view_stack);
+
+    let settings = Settings::load();
+    let filteredlisting = FilteredListing::unfiltered(&settings.default_catalog);
+    for each_sorting_type in SortType::get_all_sort_types() {
+        build_content_box(
+            ProductPageProps::default()
+                .set_user(Some(personid.to_string()))
+                .set_order(each_sorting_type.to_owned())
+                .set_pagesize(settings.page_size),
+            &authorsortby_view_stack,
+            window,
+            &filteredlisting,
+        );
+    }
+    authorsortby_view_stack.set_visible_child_name(SortType::Downloads.to_string());
+
+    // Show the real name once the profile is loaded
+    let (sender, receiver) = async_channel::bounded::<Person>(1);
+    let personid = personid.to_string();
+    adw::gio::spawn_blocking(move || match details::get_person(&personid) {
+        Ok(person) => sender.send_blocking(person).unwrap_or_default(),
+        Err(e) => eprintln!("Failed to load author {} : {}", personid, e),
+    });
+    glib::spawn_future_local(async move {
+        if let Ok(person) = receiver.recv().await {
+            header_title.set_title(&person.display_name());
+        }
+    });
+
+    dialog.set_child(Some(&dialogbox));
+    dialog.present(Some(window));
+}
+
+fn build_follow_button(personid: &str) -> ToggleButton {
+    let following = Follows::load().contains(personid);
+    let followbutton = ToggleButton::builder()
+        .label(match following {
+            true => "Following",
+            false => "Follow",
+        })
+        .tooltip_text("Get notified when this author uploads a new theme")
+        .active(following)
+        .valign(Align::Center)
+        .build();
+    let personid = personid.to_string();
+    followbutton.connect_toggled(move |followbutton| {
+        follows::set_following(&personid, followbutton.is_active());
+        followbutton.set_label(match followbutton.is_active() {
+            true => "Following",
+            false => "Follow",
+        });
+    });
+    followbutton
+}
+
+// Author name on cards and in the detail dialog, opens the author page
+fn build_author_button(title: &str, product: &Product, window: &ApplicationWindow) -> Button {
+    let authorbutton = Button::builder()
+        .child(
+            &ActionRow::builder()
+                .title(title)
+                .subtitle(&product.personid)
+                .subtitle_lines(1)
+                .activatable(false)
+                .use_markup(false)
+                .build(),
+        )
+        .tooltip_text(format!("Show all themes by {}", product.personid))
+        .css_classes(vec!["flat"])
+        .build();
+    let personid = product.personid.clone();
+    let window = window.clone();
+    authorbutton.connect_clicked(move |_| show_author_page(&personid, &window));
+    authorbutton
+}
+
+// Looks for new uploads of followed authors and posts one notification per author
+fn check_followed_authors(app: &adw::Application) {
+    if Follows::load().follows.is_empty() {
+        return;
+    }
+    let (sender, receiver) = async_channel::bounded::<Vec<(String, Vec<Product>)>>(1);
+    adw::gio::spawn_blocking(move || {
+        sender
+            .send_blocking(follows::check_new_uploads())
+            .unwrap_or_default();
+    });
+    let app = app.clone();
+    glib::spawn_future_local(async move {
+        let new_uploads = match receiver.recv().await {
+            Ok(new_uploads) => new_uploads,
+            Err(_) => return,
+        };
+        for (personid, products) in new_uploads {
+            let names: Vec<&str> = products.iter().map(|p| p.name.as_str()).collect();
+            let notification = adw::gio::Notification::new(&match products.len() {
+                1 => format!("New theme by {}", personid),
+                count => format!("{} new themes by {}", count, personid),
+            });
+            notification.set_body(Some(&names.join(", ")));
+            notification.set_default_action_and_target_value(
+                "app.show-author",
+                Some(&personid.to_variant()),
+            );
+            app.send_notification(Some(&format!("follow-{}", personid)), &notification);
+        }
+    });
+}
+
+// Confirms installs that finished or stopped at a conflict while the window was closed
+// or in the background, the open dialog already shows the outcome otherwise
+fn notify_install_done(app: &adw::Application, job: &queue::Job) {
+    if app.active_window().is_some_and(|window| window.is_active()) {
+        return;
+    }
+    let notification = match &job.state {
+        JobState::Finished(outcome) => {
+            let notification =
+                adw::gio::Notification::new(&format!("Installed {}", job.product.name));
+            notification.set_body(Some(&match outcome.record.is_complete() {
+                true => job.variant.downloadname.clone(),
+                false => format!(
+                    "{} is incomplete : {}",
+                    job.variant.downloadname,
+                    outcome.record.issues.join(", ")
+                ),
+            }));
+            if outcome.record.is_complete()
+                && let Some(directory) = outcome.record.directories.first()
+            {
+                let target = (
+                    outcome.record.catalog.get_id().to_string(),
+                    directory.clone(),
+                );
+                notification.add_button_with_target_value(
+                    "Apply",
+                    "app.apply-theme",
+                    Some(&target.to_variant()),
+                );
+            }
+            notification.add_button("Show in Installed", "app.show-installed");
+            notification.set_default_action("app.show-installed");
+            notification
+        }
+        JobState::Failed(e) => {
+            let notification =
+                adw::gio::Notification::new(&format!("Failed to install {}", job.product.name));
+            notification.set_body(Some(&format!("{} : {}", job.variant.downloadname, e)));
+            notification
+        }
+        // Nothing was installed yet, the app stays around until this is answered
+        JobState::Conflict(conflicts) => {
+            let notification = adw::gio::Notification::new(&format!(
+                "{} Is Already Installed",
+                job.product.name
+            ));
+            let described: Vec<String> = conflicts.iter().map(|c| c.describe()).collect();
+            notification.set_body(Some(&described.join("\n")));
+            for (label, response) in [
+                ("Replace", "replace"),
+                ("Install Renamed", "rename"),
+                ("Cancel", "cancel"),
+            ] {
+                notification.add_button_with_target_value(
+                    label,
+                    "app.resolve-conflict",
+                    Some(&(job.id, response.to_string()).to_variant()),
+                );
+            }
+            notification.set_default_action("app.show-queue");
+            notification
+        }
+        _ => return,
+    };
+    app.send_notification(Some(&format!("install-{}", job.id)), &notification);
+}
+
+// Everything installed through the store, grouped by catalog, with the themes in use marked
+fn build_installed_page(view_stack: &ViewStack) {
+    let installedpage = GtkBox::new(Orientation::Vertical, 10);
+    installedpage.add_css_class("background");
+    let _installedpage_viewstack =
+        view_stack.add_titled(&installedpage, Some("Installed"), "Installed");
+
+    let emptystatus = adw::StatusPage::builder()
+        .icon_name("folder-download-symbolic")
+        .title("No Themes Installed")
+        .description("Themes installed from the store show up here")
+        .vexpand(true)
+        .build();
+    let recordspage = adw::PreferencesPage::builder().vexpand(true).build();
+    installedpage.append(&emptystatus);
+    installedpage.append(&recordspage);
+
+    let groups: Rc<RefCell<Vec<PreferencesGroup>>> = Rc::new(RefCell::new(vec![]));
+    let refresh = move || {
+        for group in groups.borrow_mut().drain(..) {
+            recordspage.remove(&group);
+        }
+        let manifest = InstallManifest::load();
+        emptystatus.set_visible(manifest.records.is_empty());
+        recordspage.set_visible(!manifest.records.is_empty());
+        for catalog in Catalog::get_all_catalog_types() {
+            let records: Vec<&InstallRecord> = manifest
+                .records
+                .iter()
+                .filter(|record| record.catalog == catalog)
+                .collect();
+            if records.is_empty() {
+                continue;
+            }
+            let group = PreferencesGroup::builder()
+                .title(catalog.to_string())
+                .build();
+            let current = desktop::current_theme(&catalog);
+            for record in records {
+                group.add(&build_installed_row(record, current.as_deref()));
+            }
+            recordspage.add(&group);
+            groups.borrow_mut().push(group);
+        }
+    };
+    refresh();
+    let refresh = Rc::new(refresh);
+    installedpage.connect_map({
+        let refresh = refresh.clone();
+        move |_| refresh()
+    });
+    // Weak, the queue outlives this window and must not keep its page alive
+    queue::download_queue().connect_job_outcome({
+        let refresh = Rc::downgrade(&refresh);
+        move |_| match refresh.upgrade() {
+            Some(refresh) => {
+                refresh();
+                glib::ControlFlow::Continue
+            }
+            None => glib::ControlFlow::Break,
+        }
+    });
+    // Rows reload the page through this after changing the manifest themselves
+    let actions = adw::gio::SimpleActionGroup::new();
+    let refresh_action = adw::gio::SimpleAction::new("refresh", None);
+    refresh_action.connect_activate(move |_, _| refresh());
+    actions.add_action(&refresh_action);
+    installedpage.insert_action_group("installed", Some(&actions));
+}
+
+fn build_installed_row(record: &InstallRecord, current: Option<&str>) -> ActionRow {
+    let row = ActionRow::builder()
+        .title(glib::markup_escape_text(&record.name))
+        .subtitle(format!(
+            "{} · {} · {}",
+            glib::markup_escape_text(&record.downloadname),
+            record.scope.to_string(),
+            get_formatted_date(&record.installed)
+        ))
+        .build();
+    if !record.is_complete() {
+        row.add_css_class("warning");
+        row.set_tooltip_text(Some(
+            &(String::from("Installed, but incomplete:\n") + &record.issues.join("\n")),
+        ));
+    }
+    if !record.backups.is_empty() {
+        row.add_suffix(&build_rollback_button(record));
+    }
+    let directory = match record.directories.first() {
+        Some(directory) => directory.clone(),
+        None => return row,
+    };
+    if current.is_some_and(|current| record.directories.iter().any(|d| d == current)) {
+        row.add_suffix(
+            &Label::builder()
+                .label("In Use")
+                .css_classes(vec!["caption-heading", "accent"])
+                .build(),
+        );
+        return row;
+    }
+    let applybutton = Button::builder()
+        .label("Apply")
+        .valign(Align::Center)
+        .build();
+    let catalog = record.catalog.clone();
+    applybutton.connect_clicked(move |applybutton| {
+        match desktop::apply_theme(&catalog, &directory) {
+            Ok(_) => {
+                applybutton.set_label("In Use");
+                applybutton.set_sensitive(false);
+            }
+            Err(e) => {
+                applybutton.set_icon_name("dialog-error-symbolic");
+                applybutton.set_tooltip_text(Some(&e.to_string()));
+            }
+        }
+    });
+    row.add_suffix(&applybutton);
+    row
+}
+
+// Swaps the backup of what an install replaced back into place, then reloads the page
+fn build_rollback_button(record: &InstallRecord) -> Button {
+    let rollbackbutton = Button::builder()
+        .icon_name("edit-undo-symbolic")
+        .tooltip_text(match &record.previous {
+            Some(previous) => format!("Restore {} from before this install", previous.name),
+            None => String::from("Restore the version from before this install"),
+        })
+        .valign(Align::Center)
+        .css_classes(vec!["flat"])
+        .build();
+    let record = record.clone();
+    rollbackbutton.connect_clicked(move |rollbackbutton| {
+        rollbackbutton.set_sensitive(false);
+        rollbackbutton.set_child(Some(&Spinner::new()));
+        let (sender, receiver) = async_channel::bounded::<std::result::Result<(), String>>(1);
+        let record = record.clone();
+        adw::gio::spawn_blocking(move || {
+            let result = installer::rollback_install(&record).map_err(|e| e.to_string());
+            sender.send_blocking(result).unwrap_or_default();
+        });
+        let rollbackbutton = rollbackbutton.clone();
+        glib::spawn_future_local(async move {
+            match receiver.recv().await {
+                Ok(Ok(())) => {
+                    let _ = rollbackbutton.activate_action("installed.refresh", None);
+                }
+                Ok(Err(e)) => {
+                    rollbackbutton.set_icon_name("dialog-error-symbolic");
+                    rollbackbutton.set_tooltip_text(Some(&e));
+                }
+                Err(_) => {}
+            }
+        });
+    });
+    rollbackbutton
+}
+
+fn save_preference(change: impl FnOnce(&mut Settings)) {
+    if let Err(e) = settings::update_settings(change) {
+        eprintln!("Failed to save preferences : {}", e);
+    }
+}
+
+// Every change is saved right away. Page size, default tab and sort apply to the next start.
+fn build_preferences_dialog() -> adw::PreferencesDialog {
+    let settings = Settings::load();
+    let dialog = adw::PreferencesDialog::builder()
+        .title("Preferences")
+        .can_close(true)
+        .build();
+    let page = adw::PreferencesPage::new();
+    dialog.add(&page);
+
+    let browsinggroup = PreferencesGroup::builder()
+        .title("Browsing")
+        .description("Takes effect the next time the app starts")
+        .build();
+    let catalogs = Catalog::get_all_catalog_types();
+    let cataloglabels: Vec<&str> = catalogs.iter().map(|c| c.to_string()).collect();
+    let catalogrow = adw::ComboRow::builder()
+        .title("Default Tab")
+        .model(&gtk4::StringList::new(&cataloglabels))
+        .selected(
+            catalogs
+                .iter()
+                .position(|c| *c == settings.default_catalog)
+                .unwrap_or_default() as u32,
+        )
+        .build();
+    catalogrow.connect_selected_notify(move |catalogrow| {
+        let catalog = Catalog::get_all_catalog_types()[catalogrow.selected() as usize].clone();
+        save_preference(move |settings| settings.default_catalog = catalog);
+    });
+    let sorttypes = SortType::get_all_sort_types();
+    let sortlabels: Vec<&str> = sorttypes.iter().map(|s| s.to_string()).collect();
+    let sortrow = adw::ComboRow::builder()
+        .title("Default Sort Order")
+        .model(&gtk4::StringList::new(&sortlabels))
+        .selected(
+            sorttypes
+                .iter()
+                .position(|s| **s == settings.default_sort)
+                .unwrap_or_default() as u32,
+        )
+        .build();
+    sortrow.connect_selected_notify(move |sortrow| {
+        let sortby = SortType::get_all_sort_types()[sortrow.selected() as usize].clone();
+        save_preference(move |settings| settings.default_sort = sortby);
+    });
+    let pagesizerow = adw::SpinRow::with_range(5.0, 50.0, 5.0);
+    pagesizerow.set_title("Themes per Page");
+    pagesizerow.set_value(settings.page_size.into());
+    pagesizerow.connect_value_notify(move |pagesizerow| {
+        let page_size = pagesizerow.value() as u8;
+        save_preference(move |settings| settings.page_size = page_size);
+    });
+    let searchdelayrow = adw::SpinRow::with_range(0.0, 2000.0, 100.0);
+    searchdelayrow.set_title("Search Delay");
+    searchdelayrow.set_subtitle("Milliseconds to wait after typing");
+    searchdelayrow.set_value(settings.search_delay.into());
+    searchdelayrow.connect_value_notify(move |searchdelayrow| {
+        let search_delay = searchdelayrow.value() as u32;
+        save_preference(move |settings| settings.search_delay = search_delay);
+    });
+    browsinggroup.add(&catalogrow);
+    browsinggroup.add(&sortrow);
+    browsinggroup.add(&pagesizerow);
+    browsinggroup.add(&searchdelayrow);
+    page.add(&browsinggroup);
+
+    let installinggroup = PreferencesGroup::builder().title("Installing").build();
+    let providers = Provider::get_all_providers();
+    let providerlabels: Vec<&str> = providers.iter().map(|p| p.to_string()).collect();
+    let providerrow = adw::ComboRow::builder()
+        .title("Provider")
+        .model(&gtk4::StringList::new(&providerlabels))
+        .selected(
+            providers
+                .iter()
+                .position(|p| *p == settings.provider)
+                .unwrap_or_default() as u32,
+        )
+        .build();
+    providerrow.connect_selected_notify(move |providerrow| {
+        let provider = Provider::get_all_providers()[providerrow.selected() as usize].clone();
+        save_preference(move |settings| settings.provider = provider);
+    });
+    let installscopes = InstallScope::get_all_scopes();
+    let installscopelabels: Vec<&str> = installscopes.iter().map(|s| s.to_string()).collect();
+    let scoperow = adw::ComboRow::builder()
+        .title("Default Install Location")
+        .subtitle("Used unless a different location was picked for a catalog in the theme details")
+        .model(&gtk4::StringList::new(&installscopelabels))
+        .selected(
+            installscopes
+                .iter()
+                .position(|s| *s == settings.default_install_scope)
+                .unwrap_or_default() as u32,
+        )
+        .build();
+    scoperow.connect_selected_notify(move |scoperow| {
+        let scope = InstallScope::get_all_scopes()[scoperow.selected() as usize].clone();
+        save_preference(move |settings| settings.default_install_scope = scope);
+    });
+    let autoapplyrow = adw::SwitchRow::builder()
+        .title("Apply After Install")
+        .subtitle("Switch the desktop to a theme as soon as it is installed")
+        .active(settings.auto_apply)
+        .build();
+    autoapplyrow.connect_active_notify(move |autoapplyrow| {
+        let auto_apply = autoapplyrow.is_active();
+        save_preference(move |settings| settings.auto_apply = auto_apply);
+    });
+    installinggroup.add(&providerrow);
+    installinggroup.add(&scoperow);
+    installinggroup.add(&autoapplyrow);
+    page.add(&installinggroup);
+
+    let storagegroup = PreferencesGroup::builder()
+        .title("Storage")
+        .description("Oldest files are removed at startup once a cache grows past its limit, 0 keeps everything")
+        .build();
+    let archivecacherow = adw::SpinRow::with_range(0.0, 10000.0, 50.0);
+    archivecacherow.set_title("Download Cache (MB)");
+    archivecacherow.set_subtitle(&cache_usage(&archive_cache::archive_cache_dir()));
+    archivecacherow.set_value(settings.archive_cache_limit.into());
+    archivecacherow.connect_value_notify(move |archivecacherow| {
+        let limit = archivecacherow.value() as u32;
+        save_preference(move |settings| settings.archive_cache_limit = limit);
+    });
+    let previewcacherow = adw::SpinRow::with_range(0.0, 10000.0, 50.0);
+    previewcacherow.set_title("Preview Cache (MB)");
+    previewcacherow.set_subtitle(&cache_usage(std::path::Path::new("/tmp/themeinstaller/cache")));
+    previewcacherow.set_value(settings.preview_cache_limit.into());
+    previewcacherow.connect_value_notify(move |previewcacherow| {
+        let limit = previewcacherow.value() as u32;
+        save_preference(move |settings| settings.preview_cache_limit = limit);
+    });
+    storagegroup.add(&archivecacherow);
+    storagegroup.add(&build_cached_archives_row(&archivecacherow));
+    storagegroup.add(&previewcacherow);
+    page.add(&storagegroup);
+
+    dialog
+}
+
+fn cache_usage(cache_dir: &std::path::Path) -> String {
+    match storage::dir_size(cache_dir) {
+        0 => String::from("Empty"),
+        size => format!("{} used", format_size(size)),
+    }
+}
+
+// Every cached archive with a delete button, updates the usage shown on `archivecacherow`
+fn build_cached_archives_row(archivecacherow: &adw::SpinRow) -> ExpanderRow {
+    let archives = archive_cache::list_cached_archives();
+    let cachedrow = ExpanderRow::builder()
+        .title("Cached Downloads")
+        .subtitle(format!("{} archives", archives.len()))
+        .sensitive(!archives.is_empty())
+        .build();
+    let remaining = Rc::new(Cell::new(archives.len()));
+    for archive in archives {
+        let row = ActionRow::builder()
+            .title(glib::markup_escape_text(&archive.name))
+            .subtitle(format!(
+                "{} · {} · {}",
+                glib::markup_escape_text(&archive.downloadname),
+                format_size(archive.size),
+                get_formatted_date(&archive.downloaded)
+            ))
+            .build();
+        let deletebutton = Button::builder()
+            .icon_name("user-trash-symbolic")
+            .tooltip_text("Delete")
+            .valign(Align::Center)
+            .css_classes(vec!["flat"])
+            .build();
+        let cachedrow_clone = cachedrow.clone();
+        let archivecacherow = archivecacherow.clone();
+        let remaining = remaining.clone();
+        let row_clone = row.clone();
+        deletebutton.connect_clicked(move |deletebutton| match archive.delete() {
+            Ok(()) => {
+                cachedrow_clone.remove(&row_clone);
+                remaining.set(remaining.get() - 1);
+                cachedrow_clone.set_subtitle(&format!("{} archives", remaining.get()));
+                cachedrow_clone.set_sensitive(remaining.get() > 0);
+                archivecacherow.set_subtitle(&cache_usage(&archive_cache::archive_cache_dir()));
+            }
+            Err(e) => {
+                deletebutton.set_icon_name("dialog-error-symbolic");
+                deletebutton.set_tooltip_text(Some(&e.to_string()));
+            }
+        });
+        row.add_suffix(&deletebutton);
+        cachedrow.add_row(&row);
+    }
+    cachedrow
+}
+
+// Theme profiles: saves the current look under a name and switches between saved
+// looks, installing whatever a profile needs that is not on this machine yet
+fn build_profiles_dialog(window: &ApplicationWindow) -> adw::PreferencesDialog {
+    let dialog = adw::PreferencesDialog::builder()
+        .title("Theme Profiles")
+        .can_close(true)
+        .build();
+    let page = adw::PreferencesPage::new();
+    dialog.add(&page);
+
+    let savegroup = PreferencesGroup::builder()
+        .title("Current Look")
+        .description("Saves the GTK, Shell, icon and cursor themes in use right now. Only themes installed from the store can be saved.")
+        .build();
+    let namerow = adw::EntryRow::builder()
+        .title("Profile Name")
+        .show_apply_button(true)
+        .build();
+    savegroup.add(&namerow);
+    page.add(&savegroup);
+
+    let listgroup = PreferencesGroup::builder().title("Saved Profiles").build();
+    let importbutton = Button::builder()
+        .label("Import")
+        .valign(Align::Center)
+        .css_classes(vec!["flat"])
+        .build();
+    listgroup.set_header_suffix(Some(&importbutton));
+    page.add(&listgroup);
+
+    let profilelist = ProfileList {
+        group: listgroup.clone(),
+        rows: Rc::new(RefCell::new(vec![])),
+        dialog: dialog.clone(),
+        window: window.clone(),
+    };
+    profilelist.refresh();
+
+    let save_dialog = dialog.clone();
+    let save_profilelist = profilelist.clone();
+    namerow.connect_apply(move |namerow| {
+        let name = namerow.text().trim().to_string();
+        if name.is_empty() {
+            save_dialog.add_toast(adw::Toast::new("Enter a name for the profile"));
+            return;
+        }
+        let (profile, skipped) = Profile::capture(&name);
+        for message in &skipped {
+            save_dialog.add_toast(adw::Toast::new(message));
+        }
+        if profile.components.is_empty() {
+            save_dialog.add_toast(adw::Toast::new(
+                "None of the themes in use were installed from the store",
+            ));
+            return;
+        }
+        let mut profiles = Profiles::load();
+        profiles.upsert(profile);
+        match profiles.save() {
+            Ok(_) => {
+                namerow.set_text("");
+                save_dialog.add_toast(adw::Toast::new(&format!("Saved {}", name)));
+                save_profilelist.refresh();
+            }
+            Err(e) => {
+                save_dialog.add_toast(adw::Toast::new(&format!("Failed to save {} : {}", name, e)))
+            }
+        }
+    });
+
+    let window_clone = window.clone();
+    let import_dialog = dialog.clone();
+    importbutton.connect_clicked(move |_| {
+        let jsonfilter = FileFilter::new();
+        jsonfilter.set_name(Some("Theme Profile"));
+        jsonfilter.add_suffix("json");
+        let filedialog = FileDialog::builder()
+            .title("Import Profile")
+            .default_filter(&jsonfilter)
+            .build();
+        let import_dialog = import_dialog.clone();
+        let profilelist = profilelist.clone();
+        filedialog.open(Some(&window_clone), None::<&adw::gio::Cancellable>, move |file| {
+            let path = match file.ok().and_then(|file| file.path()) {
+                Some(path) => path,
+                None => return,
+            };
+            let message = match Profile::import(&path) {
+                Ok(profile) => {
+                    let name = profile.name.clone();
+                    let mut profiles = Profiles::load();
+                    profiles.upsert(profile);
+                    match profiles.save() {
+                        Ok(_) => {
+                            profilelist.refresh();
+                            format!("Imported {}", name)
+                        }
+                        Err(e) => format!("Import failed : {}", e),
+                    }
+                }
+                Err(e) => format!("Import failed : {}", e),
+            };
+            import_dialog.add_toast(adw::Toast::new(&message));
+        });
+    });
+    dialog
+}
+
+// Rows of the saved profiles group, rebuilt whenever the stored profiles change
+#[derive(Clone)]
+struct ProfileList {
+    group: PreferencesGroup,
+    rows: Rc<RefCell<Vec<ActionRow>>>,
+    dialog: adw::PreferencesDialog,
+    window: ApplicationWindow,
+}
+
+impl ProfileList {
+    fn refresh(&self) {
+        for row in self.rows.borrow_mut().drain(..) {
+            self.group.remove(&row);
+        }
+        let profiles = Profiles::load().profiles;
+        if profiles.is_empty() {
+            let row = ActionRow::builder()
+                .title("No profiles yet")
+                .subtitle("Save the current look above, or import a profile")
+                .build();
+            self.group.add(&row);
+            self.rows.borrow_mut().push(row);
+        }
+        for profile in profiles {
+            let row = build_profile_row(&profile, self);
+            self.group.add(&row);
+            self.rows.borrow_mut().push(row);
+        }
+    }
+}
+
+fn build_profile_row(profile: &Profile, profilelist: &ProfileList) -> ActionRow {
+    let dialog = &profilelist.dialog;
+    let window = &profilelist.window;
+    let row = ActionRow::builder()
+        .title(glib::markup_escape_text(&profile.name))
+        .subtitle(glib::markup_escape_text(&profile.summary()))
+        .build();
+    let spinner = Spinner::builder().visible(false).build();
+    let applybutton = Button::builder()
+        .label("Apply")
+        .valign(Align::Center)
+        .css_classes(vec!["suggested-action"])
+        .build();
+    let exportbutton = Button::builder()
+        .icon_name("document-save-symbolic")
+        .tooltip_text("Export")
+        .valign(Align::Center)
+        .css_classes(vec!["flat"])
+        .build();
+    let deletebutton = Button::builder()
+        .icon_name("user-trash-symbolic")
+        .tooltip_text("Delete")
+        .valign(Align::Center)
+        .css_classes(vec!["flat"])
+        .build();
+    row.add_suffix(&spinner);
+    row.add_suffix(&applybutton);
+    row.add_suffix(&exportbutton);
+    row.add_suffix(&deletebutton);
+
+    let apply_profile_clone = profile.clone();
+    let apply_dialog = dialog.clone();
+    applybutton.connect_clicked(move |applybutton| {
+        applybutton.set_sensitive(false);
+        spinner.set_visible(true);
+        let (sender, receiver) = async_channel::unbounded::<std::result::Result<String, String>>();
+        let profile = apply_profile_clone.clone();
+        adw::gio::spawn_blocking(move || {
+            for result in install_missing_components(&profile) {
+                sender.send_blocking(result).unwrap_or_default();
+            }
+        });
+        let profile = apply_profile_clone.clone();
+        let dialog = apply_dialog.clone();
+        let applybutton = applybutton.clone();
+        let spinner = spinner.clone();
+        glib::spawn_future_local(async move {
+            while let Ok(result) = receiver.recv().await {
+                let message = match result {
+                    Ok(message) => message,
+                    Err(message) => message,
+                };
+                dialog.add_toast(adw::Toast::new(&message));
+            }
+            let errors = apply_profile(&profile);
+            for error in &errors {
+                dialog.add_toast(adw::Toast::new(error));
+            }
+            if errors.is_empty() {
+                dialog.add_toast(adw::Toast::new(&format!("Applied {}", profile.name)));
+            }
+            spinner.set_visible(false);
+            applybutton.set_sensitive(true);
+        });
+    });
+
+    let export_profile = profile.clone();
+    let export_dialog = dialog.clone();
+    let window_clone = window.clone();
+    exportbutton.connect_clicked(move |_| {
+        let filedialog = FileDialog::builder()
+            .title("Export Profile")
+            .initial_name(export_profile.export_file_name())
+            .build();
+        let export_profile = export_profile.clone();
+        let export_dialog = export_dialog.clone();
+        filedialog.save(Some(&window_clone), None::<&adw::gio::Cancellable>, move |file| {
+            let path = match file.ok().and_then(|file| file.path()) {
+                Some(path) => path,
+                None => return,
+            };
+            let message = match export_profile.export(&path) {
+                Ok(_) => format!("Exported {} to {}", export_profile.name, path.display()),
+                Err(e) => format!("Export failed : {}", e),
+            };
+            export_dialog.add_toast(adw::Toast::new(&message));
+        });
+    });
+
+    let name = profile.name.clone();
+    let delete_dialog = dialog.clone();
+    let profilelist = profilelist.clone();
+    deletebutton.connect_clicked(move |_| {
+        let mut profiles = Profiles::load();
+        profiles.remove(&name);
+        match profiles.save() {
+            Ok(_) => profilelist.refresh(),
+            Err(e) => delete_dialog.add_toast(adw::Toast::new(&format!(
+                "Failed to delete {} : {}",
+                name, e
+            ))),
+        }
+    });
+    row
+}
+
+// contentbox function
+fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &ApplicationWindow) {
+    let imgpath = each_product.previewpics.first().map(|url| preview_path(url));
+    let img = Picture::builder()
+        .valign(Align::Center)
+        .hexpand_set(false)
+        .vexpand_set(false)
+        .margin_start(0)
+        .margin_end(0)
+        .margin_top(0)
+        .margin_bottom(0)
+        .css_name("img-cover")
+        .build();
+    img.add_css_class("img-round");
+    img.set_content_fit(ContentFit::Cover);
+    //img.set_filename(Some(&std::path::Path::new(imgpath.as_str())));
+    img.set_size_request(260, 260);
+    //img.set_can_shrink(true);
+    let imgclamp = Clamp::new();
+    let imagespinner = Spinner::builder()
+        .valign(Align::Center)
+        .halign(Align::Center)
+        .hexpand(true)
+        .vexpand(true)
+        .width_request(32)
+        .height_request(32)
+        .build();
+    let imagebox = GtkBox::builder()
+        .valign(Align::Center)
+        .halign(Align::Center)
+        .hexpand(true)
+        .vexpand(true)
+        .height_request(260)
+        .width_request(260)
+        .build();
+    imagebox.append(&imagespinner);
+    //imgclamp.set_child(Some(&img));
+    //println!("Setting SPinner");
+    imgclamp.set_child(Some(&imagebox));
+    imgclamp.set_tightening_threshold(256);
+    imgclamp.set_maximum_size(256);
+
+    let flowboxchild_button = Button::builder()
+        .width_request(256)
+        .css_classes(vec!["flat"])
+        .build();
+    let flowboxchild = GtkBox::builder()
+        .hexpand_set(true)
+        .vexpand_set(true)
+        .orientation(Orientation::Vertical)
+        .valign(Align::End)
+        .halign(Align::End)
+        .margin_start(10)
+        .margin_end(10)
+        .margin_top(5)
+        .margin_bottom(10)
+        .css_classes(vec!["card", "activable1"])
+        .build();
+    flowboxchild_button.set_child(Some(&flowboxchild));
+
+    let productclamp = Clamp::builder().build();
+    productclamp.set_valign(Align::Start);
+    // FilteredListing finds the product of a card through this
+    productclamp.set_widget_name(each_product.id.to_string().as_str());
+    productclamp.set_maximum_size(256);
+    productclamp.set_child(Some(&flowboxchild_button));
+    flowboxchild.append(&imgclamp);
+
+    let prodnametype_holder = GtkBox::new(Orientation::Vertical, 0);
+    prodnametype_holder.set_margin_bottom(10);
+    prodnametype_holder.set_margin_top(0);
+    prodnametype_holder.set_margin_start(0);
+    prodnametype_holder.set_margin_end(10);
+    prodnametype_holder.set_valign(Align::End);
+    prodnametype_holder.set_halign(Align::End);
+
+    let prodnamebox = GtkBox::new(Orientation::Horizontal, 0);
+    prodnamebox.append(
+        &Label::builder()
+            .label(&each_product.name)
+            .ellipsize(EllipsizeMode::End)
+            .margin_bottom(0)
+            .margin_top(5)
+            .margin_start(10)
             .margin_end(10)
             .css_classes(vec!["heading", "accent"])
             .halign(Align::Start)
             .valign(Align::Center)
-            .hexpand_set(true)
+            .hexpand(true)
             .vexpand_set(true)
             .build(),
     );
+    let cardfavoritebutton = build_favorite_button(each_product);
+    prodnamebox.append(&cardfavoritebutton);
+    prodnametype_holder.append(&prodnamebox);
     prodnametype_holder.append(
         &Label::builder()
             .label(Catalog::id_to_string(&each_product.typeid.to_string()))
@@ -1001,14 +2277,7 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
             .activatable(false)
             .build(),
     );
-    lastbox3.append(
-        &ActionRow::builder()
-            .subtitle(&each_product.personid)
-            .title("User")
-            .activatable(false)
-            .subtitle_lines(1)
-            .build(),
-    );
+    lastbox3.append(&build_author_button("User", each_product, window));
     lastbox3.append(
         &ActionRow::builder()
             .subtitle(get_formatted_date(&each_product.created))
@@ -1050,7 +2319,6 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
                 .send_blocking(String::from("imgcomplete"))
                 .unwrap_or_default();
             //println!("After sending");
-            downloadotherimages(&each_prod_clone).unwrap_or_default();
         });
 
         // The main loop executes the asynchronous block
@@ -1062,8 +2330,13 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
             async move {
                 while let Ok(message) = imagerecv_clone.recv().await {
                     if message.eq(&String::from("imgcomplete")) {
-                        imgclone.set_filename(Some(&std::path::Path::new(imgpath_clone.as_str())));
-                        imgclamp_clone.set_child(Some(&imgclone));
+                        match imgpath_clone.as_ref().filter(|path| path.exists()) {
+                            Some(path) => {
+                                imgclone.set_filename(Some(path));
+                                imgclamp_clone.set_child(Some(&imgclone));
+                            }
+                            None => imgclamp_clone.set_child(Some(&missing_preview())),
+                        }
                         //println!("Set the image after download")
                     } else {
                     }
@@ -1103,6 +2376,13 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
         let header_title =
             adw::WindowTitle::new(&product.name, "Select the variants to install below");
         dialogheader.set_title_widget(Some(&header_title));
+        // Keeps the heart on the card in sync with the one in the dialog
+        let dialogfavoritebutton = build_favorite_button(&product);
+        dialogfavoritebutton
+            .bind_property("active", &cardfavoritebutton, "active")
+            .bidirectional()
+            .build();
+        dialogheader.pack_end(&dialogfavoritebutton);
 
         let dialog_scrollbox = ScrolledWindow::builder()
             .propagate_natural_height(true)
@@ -1119,113 +2399,111 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
         dialog_scrollbox.set_child(Some(&dialogbody));
         dialogbox.append(&dialog_scrollbox);
 
-        //Insert Images in dialog body
-        let total_preview_pics = product.previewpics.len();
-        let imgpath = "/tmp/themeinstaller/cache/".to_string() + &product.previewpics[0];
-        let img = Picture::builder()
-            .valign(Align::Center)
-            .hexpand_set(true)
-            .vexpand_set(true)
-            .margin_start(0)
-            .margin_end(0)
-            .margin_top(0)
-            .margin_bottom(0)
-            .css_name("img-cover")
-            .build();
-        img.add_css_class("img-cover");
-        img.set_size_request(512, 512);
-        img.set_content_fit(ContentFit::Cover);
-        img.set_filename(Some(&std::path::Path::new(imgpath.as_str())));
+        dialogbody.append(&build_preview_gallery(&product.previewpics, &window_clone));
 
-        let each_img_box = GtkBox::builder()
-            .spacing(10)
-            .orientation(Orientation::Horizontal)
-            .vexpand(false)
-            .hexpand(false)
-            .build();
-        let prev_button = Button::builder()
-            .icon_name("go-previous-symbolic")
-            .css_classes(vec!["circular"])
-            .hexpand(true)
-            .vexpand(true)
-            .halign(Align::Center)
-            .valign(Align::Center)
-            .margin_bottom(15)
-            .margin_top(0)
+        dialog.set_child(Some(&dialogbox));
+
+        let group = PreferencesGroup::builder()
+            .title("Select Variants to Download")
             .build();
 
-        let next_button = Button::builder()
-            .icon_name("go-next-symbolic")
-            .css_classes(vec!["circular"])
-            .hexpand(true)
-            .vexpand(true)
-            .halign(Align::Center)
-            .valign(Align::Center)
-            .margin_bottom(15)
-            .margin_top(0)
+        let catalogtype = Catalog::id_to_catalog(product.typeid.to_string().as_str());
+        let installscopes = InstallScope::get_all_scopes();
+        let installscopelabels: Vec<&str> = installscopes.iter().map(|s| s.to_string()).collect();
+        let installscope = settings::get_install_scope(&catalogtype);
+        let scoperow = adw::ComboRow::builder()
+            .title("Install Location")
+            .model(&gtk4::StringList::new(&installscopelabels))
+            .selected(
+                installscopes
+                    .iter()
+                    .position(|s| *s == installscope)
+                    .unwrap_or_default() as u32,
+            )
             .build();
-        each_img_box.append(&prev_button);
-        each_img_box.append(&img);
-        each_img_box.append(&next_button);
-
-        let imgclamp = Clamp::new();
-        imgclamp.set_css_classes(&vec!["clamp"]);
-        imgclamp.set_child(Some(&each_img_box));
-        imgclamp.set_tightening_threshold(256);
-        imgclamp.set_maximum_size(256);
-        imgclamp.set_margin_top(20);
-        imgclamp.set_margin_bottom(20);
-
-        let current_index = Arc::new(Mutex::new((0, total_preview_pics)));
-        let previewpics = product.previewpics.clone();
-        let img_prev = img.clone();
-        prev_button.connect_clicked(move |_prev_button| {
-            let mut curret_index_mutex = current_index.lock().unwrap();
-            let (current_index, total_preview_pics) = curret_index_mutex.deref_mut();
-            if *current_index == 0 {
-                *current_index = *total_preview_pics - 1;
-            } else {
-                *current_index -= 1;
+        let update_scope_subtitle = {
+            let catalogtype = catalogtype.clone();
+            move |scoperow: &adw::ComboRow| {
+                let scope = &InstallScope::get_all_scopes()[scoperow.selected() as usize];
+                if let Ok(target_dir) = scope.target_dir(&catalogtype) {
+                    scoperow.set_subtitle(&target_dir.display().to_string());
+                }
             }
-            let current_index = *current_index as usize;
-            let imgpath = "/tmp/themeinstaller/cache/".to_string() + &previewpics[current_index];
-            img_prev.set_filename(Some(&std::path::Path::new(imgpath.as_str())));
-        });
-
-        let current_index = Arc::new(Mutex::new((0, total_preview_pics as i32)));
-        let previewpics_next = product.previewpics.clone();
-        let img_next = img.clone();
-        next_button.connect_clicked(move |_next_button| {
-            let mut curret_index_mutex = current_index.lock().unwrap();
-            let (current_index, total_preview_pics) = curret_index_mutex.deref_mut();
-            if *current_index == (*total_preview_pics - 1) {
-                *current_index = 0;
-            } else {
-                *current_index += 1;
+        };
+        update_scope_subtitle(&scoperow);
+        let scope_catalogtype = catalogtype.clone();
+        scoperow.connect_selected_notify(move |scoperow| {
+            let scope = &InstallScope::get_all_scopes()[scoperow.selected() as usize];
+            if let Err(e) = settings::set_install_scope(&scope_catalogtype, scope) {
+                eprintln!("Failed to save install location : {}", e);
             }
-            let current_index = *current_index as usize;
-            let imgpath =
-                "/tmp/themeinstaller/cache/".to_string() + &previewpics_next[current_index];
-            img_next.set_filename(Some(&std::path::Path::new(imgpath.as_str())));
+            update_scope_subtitle(scoperow);
         });
+        group.add(&scoperow);
 
-        dialogbody.append(&imgclamp);
-
-        dialog.set_child(Some(&dialogbox));
+        // libadwaita apps need the theme's gtk-4.0 files in ~/.config/gtk-4.0
+        let libadwaita_group = match catalogtype {
+            Catalog::Gtk4Themes => Some(build_libadwaita_group(&product, &dialog)),
+            _ => None,
+        };
 
-        let group = PreferencesGroup::builder()
-            .title("Select Variants to Download")
+        // Themes with many files get chips to narrow them down by style, color, size and part
+        let varianttags: Vec<VariantTags> = product
+            .downloaddetails
+            .iter()
+            .map(|variant| parse_variant(&variant.downloadname))
+            .collect();
+        let recommended = recommended_variant(&varianttags, desktop::prefers_dark());
+        let variantrows: Rc<RefCell<Vec<VariantRow>>> = Rc::new(RefCell::new(vec![]));
+        // Queues every checked variant still shown as if its own button was clicked
+        let installselectedbutton = Button::builder()
+            .label("Install Selected")
+            .valign(Align::Center)
+            .sensitive(false)
+            .visible(product.downloaddetails.len() > 1)
+            .css_classes(vec!["suggested-action"])
             .build();
+        group.set_header_suffix(Some(&installselectedbutton));
+        let selected_rows = variantrows.clone();
+        installselectedbutton.connect_clicked(move |_| {
+            for variant in selected_rows.borrow().iter() {
+                if !variant.selectbutton.is_active() || !variant.row.is_visible() {
+                    continue;
+                }
+                if variant.downloadbutton.is_sensitive() {
+                    variant.downloadbutton.emit_clicked();
+                }
+                variant.selectbutton.set_active(false);
+            }
+        });
+        let dimensions = dimension_values(&varianttags);
+        if !dimensions.is_empty() {
+            group.add(&build_variant_chips(&dimensions, &variantrows, &group));
+        }
 
-        for each_variant in &product.downloaddetails {
-            let downloadsize_in_mb =
-                ((each_variant.downloadsize as f32) / 100.0).to_string() + " Mb";
+        for (index, each_variant) in product.downloaddetails.iter().enumerate() {
             let row: ActionRow = ActionRow::builder()
                 .activatable(false)
                 .title(&each_variant.downloadname)
-                .subtitle(downloadsize_in_mb)
+                .subtitle(format_size(each_variant.size_in_bytes()))
                 //.css_name("card")
                 .build();
+            let selectbutton = CheckButton::builder()
+                .valign(Align::Center)
+                .tooltip_text("Select to install together with other variants")
+                .visible(product.downloaddetails.len() > 1)
+                .build();
+            row.add_prefix(&selectbutton);
+            if recommended == Some(index) {
+                row.add_suffix(
+                    &Label::builder()
+                        .label("Recommended")
+                        .valign(Align::Center)
+                        .tooltip_text("Matches your light or dark style preference")
+                        .css_classes(vec!["caption-heading", "accent"])
+                        .build(),
+                );
+            }
             let downloadbutton = Button::builder()
                 .css_classes(vec!["pill1"])
                 .icon_name("document-save-symbolic")
@@ -1233,45 +2511,102 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
                 .margin_top(10)
                 .sensitive(true)
                 .build();
+            if matches!(
+                catalogtype,
+                Catalog::Gtk4Themes | Catalog::FullIconThemes | Catalog::Cursors
+            ) {
+                row.add_suffix(&build_try_button(&product, each_variant, &catalogtype, &dialog));
+            }
             row.add_suffix(&downloadbutton);
+            variantrows.borrow_mut().push(VariantRow {
+                row: row.clone(),
+                tags: varianttags[index].clone(),
+                size: each_variant.size_in_bytes(),
+                selectbutton: selectbutton.clone(),
+                downloadbutton: downloadbutton.clone(),
+            });
+            let selected_rows = variantrows.clone();
+            let installselectedbutton_clone = installselectedbutton.clone();
+            selectbutton.connect_toggled(move |_| {
+                let selected = selected_rows
+                    .borrow()
+                    .iter()
+                    .filter(|variant| variant.selectbutton.is_active())
+                    .count();
+                installselectedbutton_clone.set_sensitive(selected > 0);
+            });
             let new_variant = each_variant.clone();
-            let catalogtype = Catalog::id_to_catalog(&product.typeid.to_string().as_str());
+            let new_product = product.clone();
+            let catalogtype = catalogtype.clone();
+            let scoperow = scoperow.clone();
+            let dialog_clone = dialog.clone();
+            let window_clone = window_clone.clone();
+            let row_clone = row.clone();
+            let refresh_libadwaita = libadwaita_group.as_ref().map(|(_, refresh)| refresh.clone());
 
-            let (senderdownload, receiverdownload) = async_channel::unbounded::<String>();
             downloadbutton.connect_clicked(move |downloadbutton| {
-                downloadbutton.set_child(Some(&Spinner::new()));
-                eprintln!("Clicked!");
-
-                let sender = senderdownload.clone();
-                let catalogtype_arc = Arc::new(Mutex::new(catalogtype.clone()));
-                let new_variant_clone = new_variant.clone();
-                // Run async code to get all required values for populating full icon themes
-                adw::gio::spawn_blocking(move || {
-                    let catalogtype_mutex = catalogtype_arc.lock().unwrap();
-                    let catalogtype = catalogtype_mutex.deref();
-                    let _ = install_theme(&new_variant_clone, &catalogtype);
-                    sender
-                        .send_blocking("downloaded".to_string())
-                        .unwrap_or_default();
-                });
-
-                // The main loop executes the asynchronous block
-                let receiverdownload_clone = receiverdownload.clone();
-                let downloadbutton_clone = downloadbutton.clone();
-                glib::spawn_future_local({
-                    async move {
-                        while let Ok(message) = receiverdownload_clone.recv().await {
-                            if message.eq(&String::from("downloaded")) {
-                                downloadbutton_clone.set_icon_name("ephy-download-done-symbolic");
-                                downloadbutton_clone.set_sensitive(false);
-                            } else {
+                downloadbutton.set_sensitive(false);
+                let scope = InstallScope::get_all_scopes()[scoperow.selected() as usize].clone();
+                let downloadbutton = downloadbutton.clone();
+                let dialog_clone = dialog_clone.clone();
+                let window_clone = window_clone.clone();
+                let row_clone = row_clone.clone();
+                let refresh_libadwaita = refresh_libadwaita.clone();
+                queue::download_queue().enqueue(
+                    &new_product,
+                    &new_variant,
+                    &catalogtype,
+                    &scope,
+                    move |job| match &job.state {
+                        JobState::Pending => {
+                            downloadbutton.set_icon_name("document-open-recent-symbolic");
+                            downloadbutton.set_tooltip_text(Some("Waiting for other downloads"));
+                        }
+                        JobState::Active => {
+                            downloadbutton.set_child(Some(&Spinner::new()));
+                            downloadbutton.set_tooltip_text(None);
+                        }
+                        JobState::Finished(outcome) => {
+                            // Stays clickable when the install turned out incomplete
+                            downloadbutton.set_sensitive(true);
+                            show_validation(outcome, &row_clone, &downloadbutton);
+                            if let Some(refresh_libadwaita) = &refresh_libadwaita {
+                                refresh_libadwaita();
+                            }
+                            desktop::reload_if_active(&outcome.record);
+                            // Closed meanwhile, the notification offers Apply instead
+                            if dialog_clone.is_mapped() {
+                                apply_after_install(outcome, &dialog_clone);
+                                show_install_outcome(outcome, &dialog_clone, &window_clone);
                             }
                         }
-                    }
-                });
+                        // Clicking again queues another attempt
+                        JobState::Failed(e) => {
+                            downloadbutton.set_icon_name("dialog-error-symbolic");
+                            downloadbutton.set_tooltip_text(Some(e));
+                            downloadbutton.set_sensitive(true);
+                        }
+                        // Asked here while the product is open, the queue panel offers it later
+                        JobState::Conflict(_) => {
+                            downloadbutton.set_icon_name("dialog-warning-symbolic");
+                            downloadbutton.set_tooltip_text(Some("Already installed"));
+                            if dialog_clone.is_mapped() {
+                                queue::show_conflict_dialog(job, &dialog_clone);
+                            } else if window_clone.is_visible() {
+                                queue::show_conflict_dialog(job, &window_clone);
+                            }
+                        }
+                        JobState::Cancelled => {
+                            downloadbutton.set_icon_name("document-save-symbolic");
+                            downloadbutton.set_tooltip_text(None);
+                            downloadbutton.set_sensitive(true);
+                        }
+                    },
+                );
             });
             group.add(&row);
         }
+        update_variant_summary(&group, &variantrows.borrow());
         // Add the ListBox to the dialog
         let productbox = GtkBox::new(Orientation::Vertical, 5);
         dialogbody.append(&productbox);
@@ -1325,14 +2660,9 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
                 .build(),
         );
 
-        productlistrow.append(
-            &ActionRow::builder()
-                .activatable(false)
-                .title("Updated By")
-                .halign(Align::Baseline)
-                .subtitle(&product.personid)
-                .build(),
-        );
+        let authorbutton = build_author_button("Updated By", &product, &window_clone);
+        authorbutton.set_halign(Align::Baseline);
+        productlistrow.append(&authorbutton);
 
         productlistbox.append(&productlistrow);
 
@@ -1384,13 +2714,15 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
             .margin_start(10)
             .build();
 
-        expander_box.append(
-            &Label::builder()
-                .label(&product.description)
-                .wrap(true)
-                .css_classes(vec!["caption", "dimmed"])
-                .build(),
-        );
+        let descriptionlabel = Label::builder()
+            .label(html_to_markup(&product.description_html))
+            .use_markup(true)
+            .wrap(true)
+            .xalign(0.0)
+            .css_classes(vec!["caption", "dimmed"])
+            .build();
+        connect_links(&descriptionlabel, &window_clone);
+        expander_box.append(&descriptionlabel);
 
         descriptionrow.add_row(&expander_box);
 
@@ -1411,19 +2743,616 @@ fn build_flowbox_for_page(each_product: &Product, flowbox: &FlowBox, window: &Ap
                 .maximum_size(500)
                 .build(),
         );
+        if let Some((libadwaita_group, _)) = &libadwaita_group {
+            productbox.append(
+                &adw::Clamp::builder()
+                    .child(libadwaita_group)
+                    .maximum_size(500)
+                    .margin_top(20)
+                    .build(),
+            );
+        }
         productbox.append(
             &adw::Clamp::builder()
                 .child(&descriptionlistrow)
                 .maximum_size(500)
                 .build(),
         );
+        productbox.append(
+            &adw::Clamp::builder()
+                .child(&build_product_details(&product, &window_clone))
+                .maximum_size(500)
+                .margin_bottom(20)
+                .build(),
+        );
         dialog.present(Some(&window_clone));
     });
 }
+// Opens a web link through the desktop, the portal takes care of it inside Flatpak
+fn open_uri(uri: &str, window: &ApplicationWindow) {
+    gtk4::UriLauncher::new(uri).launch(
+        Some(window),
+        None::<&adw::gio::Cancellable>,
+        |result| {
+            if let Err(e) = result {
+                eprintln!("Failed to open link : {}", e);
+            }
+        },
+    );
+}
+
+// Links in description markup go through open_uri instead of the default handler
+fn connect_links(label: &Label, window: &ApplicationWindow) {
+    let window = window.clone();
+    label.connect_activate_link(move |_, uri| {
+        if is_safe_uri(uri) {
+            open_uri(uri, &window);
+        }
+        glib::Propagation::Stop
+    });
+}
+
+fn store_page_uri(product: &Product) -> String {
+    match product.detailpage.is_empty() {
+        true => format!(
+            "https://{}/p/{}/",
+            Settings::load().provider.get_base_url(),
+            product.id
+        ),
+        false => product.detailpage.clone(),
+    }
+}
+
+// Version, license, changelog, author and comments. Starts from the listing data
+// and fills in the rest once the full details are fetched.
+fn build_product_details(product: &Product, window: &ApplicationWindow) -> GtkBox {
+    let detailsbox = GtkBox::new(Orientation::Vertical, 20);
+    let unknown = |value: &str| match value.is_empty() {
+        true => String::from("Not specified"),
+        false => value.to_string(),
+    };
+
+    let aboutgroup = PreferencesGroup::builder().title("About").build();
+    let versionrow = ActionRow::builder()
+        .title("Version")
+        .subtitle(unknown(&product.version))
+        .use_markup(false)
+        .build();
+    let licenserow = ActionRow::builder()
+        .title("License")
+        .subtitle(unknown(&product.license))
+        .use_markup(false)
+        .build();
+    let homepagerow = ActionRow::builder()
+        .title("Homepage")
+        .activatable(true)
+        .use_markup(false)
+        .visible(false)
+        .build();
+    homepagerow.add_suffix(&Image::from_icon_name("adw-external-link-symbolic"));
+    let storepagerow = ActionRow::builder()
+        .title("Store Page")
+        .subtitle(store_page_uri(product))
+        .activatable(true)
+        .use_markup(false)
+        .build();
+    storepagerow.add_suffix(&Image::from_icon_name("adw-external-link-symbolic"));
+    let changelogrow = ExpanderRow::builder()
+        .title("Changelog")
+        .visible(false)
+        .build();
+    let changeloglabel = Label::builder()
+        .wrap(true)
+        .xalign(0.0)
+        .use_markup(true)
+        .margin_top(10)
+        .margin_bottom(10)
+        .margin_start(10)
+        .margin_end(10)
+        .css_classes(vec!["caption"])
+        .build();
+    connect_links(&changeloglabel, window);
+    changelogrow.add_row(&changeloglabel);
+    aboutgroup.add(&versionrow);
+    aboutgroup.add(&licenserow);
+    aboutgroup.add(&homepagerow);
+    aboutgroup.add(&storepagerow);
+    aboutgroup.add(&changelogrow);
+    detailsbox.append(&aboutgroup);
+
+    let authorgroup = PreferencesGroup::builder().title("Author").build();
+    let avatar = adw::Avatar::new(40, Some(&product.personid), true);
+    let authorrow = ActionRow::builder()
+        .title(&product.personid)
+        .activatable(true)
+        .use_markup(false)
+        .build();
+    authorrow.add_prefix(&avatar);
+    authorrow.add_suffix(&Image::from_icon_name("go-next-symbolic"));
+    let personid = product.personid.clone();
+    let window_clone = window.clone();
+    authorrow.connect_activated(move |_| show_author_page(&personid, &window_clone));
+    authorgroup.add(&authorrow);
+    detailsbox.append(&authorgroup);
+
+    let commentsgroup = PreferencesGroup::builder()
+        .title("Comments")
+        .description("Loading...")
+        .build();
+    detailsbox.append(&commentsgroup);
+
+    let storepage_uri = store_page_uri(product);
+    let window_clone = window.clone();
+    storepagerow.connect_activated(move |_| open_uri(&storepage_uri, &window_clone));
+
+    let (sender, receiver) = async_channel::bounded::<ProductPage>(1);
+    let listing_product = product.clone();
+    adw::gio::spawn_blocking(move || {
+        sender
+            .send_blocking(fetch_product_page(&listing_product))
+            .unwrap_or_default();
+    });
+    let window = window.clone();
+    glib::spawn_future_local(async move {
+        let productpage = match receiver.recv().await {
+            Ok(productpage) => productpage,
+            Err(_) => return,
+        };
+        match &productpage.product {
+            Ok(product) => {
+                versionrow.set_subtitle(&unknown(&product.version));
+                licenserow.set_subtitle(&unknown(&product.license));
+                if is_safe_uri(&product.homepage) {
+                    homepagerow.set_subtitle(&product.homepage);
+                    homepagerow.set_visible(true);
+                    let homepage = product.homepage.clone();
+                    let window = window.clone();
+                    homepagerow.connect_activated(move |_| open_uri(&homepage, &window));
+                }
+                let changelog = html_to_markup(&product.changelog);
+                if !changelog.is_empty() {
+                    changeloglabel.set_markup(&changelog);
+                    changelogrow.set_visible(true);
+                }
+            }
+            Err(e) => eprintln!("Failed to load product details : {}", e),
+        }
+
+        match &productpage.author {
+            Ok(person) => {
+                authorrow.set_title(&person.display_name());
+                authorrow.set_subtitle(&person.personid);
+                avatar.set_text(Some(&person.display_name()));
+            }
+            Err(e) => eprintln!("Failed to load author : {}", e),
+        }
+        if let Some(path) = &productpage.avatar
+            && let Ok(texture) = gdk::Texture::from_filename(path)
+        {
+            avatar.set_custom_image(Some(&texture));
+        }
+        for work in &productpage.other_works {
+            let workrow = ActionRow::builder()
+                .title(&work.name)
+                .subtitle(Catalog::id_to_string(&work.typeid.to_string()))
+                .activatable(true)
+                .use_markup(false)
+                .build();
+            workrow.add_suffix(&Image::from_icon_name("adw-external-link-symbolic"));
+            let uri = store_page_uri(work);
+            let window = window.clone();
+            workrow.connect_activated(move |_| open_uri(&uri, &window));
+            authorgroup.add(&workrow);
+        }
+
+        match &productpage.comments {
+            Ok(comments) if comments.is_empty() => {
+                commentsgroup.set_description(Some("No comments yet"));
+            }
+            Ok(comments) => {
+                commentsgroup.set_description(None);
+                for comment in comments {
+                    add_comment_rows(&commentsgroup, comment, 0);
+                }
+            }
+            Err(e) => {
+                commentsgroup.set_description(Some(&format!("Comments could not be loaded : {}", e)));
+            }
+        }
+    });
+    detailsbox
+}
+
+// One row per comment, replies are indented below their parent
+fn add_comment_rows(group: &PreferencesGroup, comment: &Comment, depth: i32) {
+    let date = match DateTime::parse_from_rfc3339(&comment.date) {
+        Ok(_) => get_formatted_date(&comment.date),
+        Err(_) => comment.date.clone(),
+    };
+    let row = ActionRow::builder()
+        .title(glib::markup_escape_text(&format!("{} · {}", comment.user, date)))
+        .subtitle(html_to_markup(&comment.text))
+        .margin_start(24 * depth)
+        .build();
+    row.add_css_class("property");
+    group.add(&row);
+    for child in &comment.children {
+        add_comment_rows(group, child, depth + 1);
+    }
+}
+
+// Applies an installed GTK theme to libadwaita apps, or reverts to what was
+// in ~/.config/gtk-4.0 before. The returned closure reloads the installed list.
+fn build_libadwaita_group(
+    product: &Product,
+    dialog: &adw::PreferencesDialog,
+) -> (PreferencesGroup, Rc<dyn Fn()>) {
+    let group = PreferencesGroup::builder()
+        .title("libadwaita Apps")
+        .description("libadwaita apps ignore the GTK theme setting. Applying places the theme's gtk-4.0 files in ~/.config/gtk-4.0, your own files there are backed up and restored on revert.")
+        .build();
+    let themerow = adw::ComboRow::builder().title("Installed Variant").build();
+    let copyrow = adw::SwitchRow::builder()
+        .title("Copy Files")
+        .subtitle("Keeps working if the theme is removed, but does not follow theme updates")
+        .build();
+    let applyrow = ActionRow::builder().title("Use for libadwaita Apps").build();
+    let applybutton = Button::builder()
+        .label("Apply")
+        .valign(Align::Center)
+        .css_classes(vec!["suggested-action"])
+        .build();
+    let revertbutton = Button::builder()
+        .label("Revert")
+        .valign(Align::Center)
+        .build();
+    applyrow.add_suffix(&revertbutton);
+    applyrow.add_suffix(&applybutton);
+    group.add(&themerow);
+    group.add(&copyrow);
+    group.add(&applyrow);
+
+    let theme_dirs: Rc<RefCell<Vec<PathBuf>>> = Rc::new(RefCell::new(vec![]));
+    let productid = product.id;
+    let refresh: Rc<dyn Fn()> = Rc::new({
+        let theme_dirs = theme_dirs.clone();
+        let themerow = themerow.clone();
+        let applyrow = applyrow.clone();
+        let applybutton = applybutton.clone();
+        let revertbutton = revertbutton.clone();
+        move || {
+            let mut dirs: Vec<PathBuf> = vec![];
+            for record in InstallManifest::load().records {
+                if record.productid != productid || record.catalog != Catalog::Gtk4Themes {
+                    continue;
+                }
+                for directory in &record.directories {
+                    let theme_dir = PathBuf::from(&record.location).join(directory);
+                    if theme_dir.join("gtk-4.0/gtk.css").exists() && !dirs.contains(&theme_dir) {
+                        dirs.push(theme_dir);
+                    }
+                }
+            }
+            let labels: Vec<String> = dirs
+                .iter()
+                .map(|d| d.file_name().unwrap_or_default().to_string_lossy().to_string())
+                .collect();
+            let labels: Vec<&str> = labels.iter().map(|l| l.as_str()).collect();
+            themerow.set_model(Some(&gtk4::StringList::new(&labels)));
+
+            let active = Gtk4ThemeState::load();
+            if let Some(state) = &active
+                && let Some(index) = dirs.iter().position(|d| d.display().to_string() == state.theme_dir)
+            {
+                themerow.set_selected(index as u32);
+            }
+            applyrow.set_subtitle(&match (&active, dirs.is_empty()) {
+                (Some(state), _) => format!("Currently using {}", state.theme_name()),
+                (None, true) => String::from("Install a variant with GTK4 support first"),
+                (None, false) => String::from("Not applied"),
+            });
+            applybutton.set_sensitive(!dirs.is_empty());
+            revertbutton.set_sensitive(active.is_some());
+            *theme_dirs.borrow_mut() = dirs;
+        }
+    });
+    refresh();
+
+    let apply_refresh = refresh.clone();
+    let apply_dialog = dialog.clone();
+    applybutton.connect_clicked(move |_| {
+        let theme_dir = match theme_dirs.borrow().get(themerow.selected() as usize) {
+            Some(theme_dir) => theme_dir.clone(),
+            None => return,
+        };
+        let mode = match copyrow.is_active() {
+            true => LinkMode::Copy,
+            false => LinkMode::Link,
+        };
+        let message = match apply_gtk4_theme(&theme_dir, &mode) {
+            Ok(_) => String::from("Applied. Restart libadwaita apps to see the change"),
+            Err(e) => format!("Failed to apply theme : {}", e),
+        };
+        apply_dialog.add_toast(adw::Toast::new(&message));
+        apply_refresh();
+    });
+    let revert_refresh = refresh.clone();
+    let revert_dialog = dialog.clone();
+    revertbutton.connect_clicked(move |_| {
+        let message = match revert_gtk4_theme() {
+            Ok(_) => String::from("Restored your previous ~/.config/gtk-4.0"),
+            Err(e) => format!("Failed to revert : {}", e),
+        };
+        revert_dialog.add_toast(adw::Toast::new(&message));
+        revert_refresh();
+    });
+
+    (group, refresh)
+}
+
+struct VariantRow {
+    row: ActionRow,
+    tags: VariantTags,
+    size: u64,
+    selectbutton: CheckButton,
+    downloadbutton: Button,
+}
+
+// Count and total download size of the variants the chips leave visible
+fn update_variant_summary(group: &PreferencesGroup, rows: &[VariantRow]) {
+    let visible: Vec<&VariantRow> = rows.iter().filter(|r| r.row.is_visible()).collect();
+    let total = format_size(visible.iter().map(|r| r.size).sum());
+    let description = match (visible.len(), rows.len()) {
+        (_, 0 | 1) => None,
+        (shown, all) if shown == all => Some(format!("{} variants, {} in total", all, total)),
+        (shown, all) => Some(format!("{} of {} variants, {} in total", shown, all, total)),
+    };
+    group.set_description(description.as_deref());
+}
+
+// Filter chips for the ways the variants differ. Within one dimension any selected
+// value passes, across dimensions all of them have to match.
+fn build_variant_chips(
+    dimensions: &[(Dimension, Vec<String>)],
+    rows: &Rc<RefCell<Vec<VariantRow>>>,
+    group: &PreferencesGroup,
+) -> adw::PreferencesRow {
+    let chipsbox = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .margin_top(10)
+        .margin_bottom(10)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    let selection = Rc::new(RefCell::new(
+        dimensions
+            .iter()
+            .map(|(dimension, _)| (dimension.clone(), BTreeSet::new()))
+            .collect::<Vec<_>>(),
+    ));
+    for (dimension, values) in dimensions {
+        let dimensionbox = GtkBox::new(Orientation::Horizontal, 6);
+        dimensionbox.append(
+            &Label::builder()
+                .label(dimension.to_string())
+                .width_chars(6)
+                .xalign(0.0)
+                .css_classes(vec!["caption-heading", "dimmed"])
+                .build(),
+        );
+        let chips = adw::WrapBox::builder()
+            .child_spacing(6)
+            .line_spacing(6)
+            .hexpand(true)
+            .build();
+        for value in values {
+            let chip = ToggleButton::builder()
+                .label(value)
+                .css_classes(vec!["pill", "caption"])
+                .build();
+            let dimension = dimension.clone();
+            let value = value.clone();
+            let selection = selection.clone();
+            let rows = rows.clone();
+            let group = group.clone();
+            chip.connect_toggled(move |chip| {
+                let mut selection = selection.borrow_mut();
+                if let Some((_, values)) = selection.iter_mut().find(|(d, _)| *d == dimension) {
+                    match chip.is_active() {
+                        true => values.insert(value.clone()),
+                        false => values.remove(&value),
+                    };
+                }
+                let rows = rows.borrow();
+                for variant in rows.iter() {
+                    let visible = matches_selection(&variant.tags, &selection);
+                    variant.row.set_visible(visible);
+                    // A variant filtered out of view is not installed by Install Selected
+                    if !visible {
+                        variant.selectbutton.set_active(false);
+                    }
+                }
+                update_variant_summary(&group, &rows);
+            });
+            chips.append(&chip);
+        }
+        dimensionbox.append(&chips);
+        chipsbox.append(&dimensionbox);
+    }
+    adw::PreferencesRow::builder()
+        .activatable(false)
+        .child(&chipsbox)
+        .build()
+}
+
+// Previews a variant before anything is installed, sample widgets for GTK themes,
+// an icon grid for icon themes and the common shapes for cursor themes
+fn build_try_button(
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+    catalog: &Catalog,
+    dialog: &adw::PreferencesDialog,
+) -> Button {
+    let trybutton = Button::builder()
+        .icon_name("view-reveal-symbolic")
+        .tooltip_text(match catalog {
+            Catalog::FullIconThemes => "Preview Icons",
+            Catalog::Cursors => "Preview Cursors",
+            _ => "Try It",
+        })
+        .margin_bottom(10)
+        .margin_top(10)
+        .build();
+    let product = product.clone();
+    let downloaddetail = downloaddetail.clone();
+    let catalog = catalog.clone();
+    let dialog = dialog.clone();
+    trybutton.connect_clicked(move |trybutton| {
+        trybutton.set_sensitive(false);
+        trybutton.set_child(Some(&Spinner::new()));
+        let (sender, receiver) =
+            async_channel::bounded::<std::result::Result<ThemePreview, String>>(1);
+        let product = product.clone();
+        let downloaddetail = downloaddetail.clone();
+        let catalog = catalog.clone();
+        adw::gio::spawn_blocking(move || {
+            let result =
+                prepare_preview(&product, &downloaddetail, &catalog).map_err(|e| e.to_string());
+            sender.send_blocking(result).unwrap_or_default();
+        });
+        let trybutton = trybutton.clone();
+        let dialog = dialog.clone();
+        glib::spawn_future_local(async move {
+            if let Ok(result) = receiver.recv().await {
+                let result = result.and_then(|preview| {
+                    theme_preview::show_preview(preview, &dialog).map_err(|e| e.to_string())
+                });
+                if let Err(e) = result {
+                    dialog.add_toast(adw::Toast::new(&format!("Preview failed : {}", e)));
+                }
+            }
+            trybutton.set_icon_name("view-reveal-symbolic");
+            trybutton.set_sensitive(true);
+        });
+    });
+    trybutton
+}
+
+// Marks a variant row as installed, or as incomplete when validation found problems
+fn show_validation(outcome: &InstallOutcome, row: &ActionRow, downloadbutton: &Button) {
+    let compatibility: Vec<String> = outcome
+        .validation
+        .iter()
+        .map(|report| report.compatibility())
+        .filter(|compatibility| !compatibility.is_empty())
+        .collect();
+    let mut subtitle = row.subtitle().map(|s| s.to_string()).unwrap_or_default();
+    if !compatibility.is_empty() {
+        subtitle = format!("{} · {}", subtitle, compatibility.join(" / "));
+    }
+
+    if outcome.record.is_complete() {
+        downloadbutton.set_icon_name("ephy-download-done-symbolic");
+        downloadbutton.set_sensitive(false);
+    } else {
+        subtitle = format!("{} · Incomplete", subtitle);
+        downloadbutton.set_icon_name("dialog-warning-symbolic");
+        downloadbutton.set_tooltip_text(Some(
+            &(String::from("Installed, but incomplete:\n") + &outcome.record.issues.join("\n")),
+        ));
+        row.add_css_class("warning");
+    }
+    row.set_subtitle(&subtitle);
+}
+
+// Switches the desktop to a freshly installed theme when the user asked for that
+fn apply_after_install(outcome: &InstallOutcome, dialog: &adw::PreferencesDialog) {
+    if !Settings::load().auto_apply || !outcome.record.is_complete() {
+        return;
+    }
+    let directory = match outcome.record.directories.first() {
+        Some(directory) => directory,
+        None => return,
+    };
+    let message = match desktop::apply_theme(&outcome.record.catalog, directory) {
+        Ok(_) => format!("Applied {}", directory),
+        Err(e) => format!("Failed to apply {} : {}", directory, e),
+    };
+    dialog.add_toast(adw::Toast::new(&message));
+}
+
+// Tells the user about anything an install left unresolved, offering to fetch
+// missing parent icon themes from the store
+fn show_install_outcome(
+    outcome: &InstallOutcome,
+    dialog: &adw::PreferencesDialog,
+    window: &ApplicationWindow,
+) {
+    if outcome.missing_parents.is_empty() {
+        for warning in &outcome.warnings {
+            dialog.add_toast(adw::Toast::new(warning));
+        }
+        return;
+    }
+
+    let (inherited, missing) = match outcome.record.catalog {
+        Catalog::Cursors => ("cursors", "Cursors"),
+        _ => ("icons", "Icons"),
+    };
+    let mut body = format!(
+        "{} inherits {} from {}, which {} not installed. {} it does not ship itself will be missing.",
+        outcome.record.name,
+        inherited,
+        outcome.missing_parents.join(", "),
+        if outcome.missing_parents.len() == 1 { "is" } else { "are" },
+        missing
+    );
+    for warning in &outcome.warnings {
+        body.push_str("\n\n");
+        body.push_str(warning);
+    }
+    let alert = adw::AlertDialog::new(Some("Missing Parent Themes"), Some(&body));
+    alert.add_responses(&[("ignore", "Ignore"), ("fetch", "Install from Store")]);
+    alert.set_response_appearance("fetch", adw::ResponseAppearance::Suggested);
+    alert.set_default_response(Some("fetch"));
+
+    let missing_parents = outcome.missing_parents.clone();
+    let catalog = outcome.record.catalog.clone();
+    let scope = outcome.record.scope.clone();
+    let dialog = dialog.clone();
+    alert.connect_response(None, move |_, response| {
+        if response != "fetch" {
+            return;
+        }
+        let (sender, receiver) = async_channel::unbounded::<std::result::Result<String, String>>();
+        let missing_parents = missing_parents.clone();
+        let catalog = catalog.clone();
+        let scope = scope.clone();
+        adw::gio::spawn_blocking(move || {
+            for result in install_missing_parents(&missing_parents, &catalog, &scope) {
+                sender.send_blocking(result).unwrap_or_default();
+            }
+        });
+        let dialog = dialog.clone();
+        glib::spawn_future_local(async move {
+            while let Ok(result) = receiver.recv().await {
+                let message = match result {
+                    Ok(message) => message,
+                    Err(message) => message,
+                };
+                dialog.add_toast(adw::Toast::new(&message));
+            }
+        });
+    });
+    alert.present(Some(window));
+}
+
 fn build_content_box(
     productpage: &ProductPageProps,
     themecategorysortby_view_stack: &ViewStack,
     window: &ApplicationWindow,
+    filteredlisting: &FilteredListing,
 ) {
     let themecategory_contentbox = GtkBox::new(Orientation::Vertical, 20);
     //window.set_height_request(1024);
@@ -1437,7 +3366,10 @@ fn build_content_box(
     spinner_loading_themecategory_latest.set_height_request(48);
 
     let spinner_label_themecategory_latest = Label::builder()
-        .label(String::from("Fetching ") + productpage.cat.to_string() + ". Please wait...")
+        .label(match &productpage.user {
+            Some(user) => format!("Fetching themes by {}. Please wait...", user),
+            None => String::from("Fetching ") + productpage.cat.to_string() + ". Please wait...",
+        })
         .css_classes(vec!["dimmed", "Heading-4"])
         .build();
 
@@ -1539,10 +3471,13 @@ fn build_content_box(
 
     // The main loop executes the asynchronous block
     let window: ApplicationWindow = window.clone();
+    filteredlisting.attach(&flowbox);
+    let filteredlisting = filteredlisting.clone();
     glib::spawn_future_local({
         async move {
             if let Ok(productcatalog) = receiver.recv().await {
                 for each_product in productcatalog.data {
+                    filteredlisting.add_product(&each_product);
                     build_flowbox_for_page(&each_product, &flowbox, &window);
                 }
                 themecategory_loadingpage.remove(&themecategory_contentbox);
@@ -1551,6 +3486,7 @@ fn build_content_box(
 
             while let Ok(productcatalog) = loadmorereceiver.recv().await {
                 for each_product in productcatalog.data {
+                    filteredlisting.add_product(&each_product);
                     build_flowbox_for_page(&each_product, &flowbox, &window);
                     //loadmorebox.set_child(&None);
                     loadmorebox.set_child(Some(&Image::from_icon_name("go-down-symbolic")));
@@ -1561,13 +3497,15 @@ fn build_content_box(
     });
 }
 
+// Builds the result grid of the search page. Returns a closure that starts a
+// fresh search from the first page with the current `searchpageprops`.
 fn build_search_content_box(
     searchentry: &SearchEntry,
     searchresultpage: &GtkBox,
     window: &ApplicationWindow,
-) {
+    searchpageprops: &Rc<RefCell<SearchPageProps>>,
+) -> Rc<dyn Fn()> {
     let search_contentbox = GtkBox::new(Orientation::Vertical, 20);
-    println!("Inside the seach content box");
     search_contentbox.set_widget_name("SearchContentBox");
     //window.set_height_request(1024);
     search_contentbox.set_valign(Align::Center);
@@ -1597,12 +3535,11 @@ fn build_search_content_box(
     flowbox.set_selection_mode(SelectionMode::None);
     flowbox.set_css_classes(&vec!["suggested-action"]);
     let flowboxrevealer = adw::gtk::Revealer::new();
-        flowboxrevealer.set_transition_type(adw::gtk::RevealerTransitionType::Crossfade);
-        flowboxrevealer.set_transition_duration(3000); // in milliseconds
-        flowboxrevealer.set_child(Some(&flowbox));
+    flowboxrevealer.set_transition_type(adw::gtk::RevealerTransitionType::Crossfade);
+    flowboxrevealer.set_transition_duration(3000); // in milliseconds
+    flowboxrevealer.set_child(Some(&flowbox));
     flowboxrevealer.set_reveal_child(true);
 
-
     let scrollwindow = ScrolledWindow::builder()
         .hexpand(true)
         .vexpand(true)
@@ -1616,97 +3553,317 @@ fn build_search_content_box(
     flowcontentbox.set_vexpand(true);
     flowcontentbox.set_hexpand(true);
 
+    let noresultslabel = Label::builder()
+        .label("No themes found")
+        .css_classes(vec!["dimmed", "title-4"])
+        .margin_top(30)
+        .visible(false)
+        .build();
+    let loadmorespinner = Spinner::builder()
+        .height_request(24)
+        .width_request(24)
+        .margin_bottom(15)
+        .visible(false)
+        .build();
+    // Shown instead of the spinner when a further page failed, scrolling does not retry it
+    let loadmoreerrorlabel = Label::builder()
+        .css_classes(vec!["dimmed"])
+        .wrap(true)
+        .build();
+    let loadmoreretrybutton = Button::builder()
+        .label("Retry")
+        .halign(Align::Center)
+        .css_classes(vec!["pill"])
+        .build();
+    let loadmoreerrorbox = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .margin_bottom(15)
+        .visible(false)
+        .build();
+    loadmoreerrorbox.append(&loadmoreerrorlabel);
+    loadmoreerrorbox.append(&loadmoreretrybutton);
+    flowcontentbox.append(&noresultslabel);
     flowcontentbox.append(&flowboxrevealer);
+    flowcontentbox.append(&loadmorespinner);
+    flowcontentbox.append(&loadmoreerrorbox);
     scrollwindow.set_child(Some(&flowcontentbox));
-    println!("Inside the search content box flowcontentbox");
-    let (sender, receiver) = async_channel::unbounded::<(String, ProductCatalog)>();
-    //let (loadmoresender, loadmorereceiver) = async_channel::unbounded::<ProductCatalog>();
-    //let productpage_ref = Arc::new(Mutex::new(searchpageprops.clone()));
-    //let loadmore_productpage_ref: Arc<Mutex<SearchPageProps>> = Arc::clone(&productpage_ref);
-    let productpage = SearchPageProps::default(searchentry.text().to_string());
-
-    //let contentbox_productpage_ref = Arc::clone(&productpage_ref);
-    println!("Inside the seach content box2");
-    let firstloadsender = sender.clone();
-    let product_ref = Arc::new(Mutex::new(productpage));
-    let product_loadmore_ref = Arc::clone(&product_ref);
-    //let flowbox_clone: FlowBox = flowbox.clone();
-    let flowboxrevealer_clone = flowboxrevealer.clone();
-    searchentry.connect_search_changed(move |searchentry| {
 
-        println!("search_contentbox widget has been changed");
-        let sender = firstloadsender.clone();
-        let sender_ref = Arc::new(Mutex::new(sender.clone()));
-        let mut productpage_mutex = product_loadmore_ref.lock().unwrap();
-        let productpage = productpage_mutex.deref_mut();
-        productpage.set_search_text(searchentry.text().to_string());
-        let productpage_ref = Arc::new(Mutex::new(productpage.clone()));
-        //let productpage_ref = Arc::clone(&contentbox_productpage_ref);
-
-        //let flowbox_ref = Arc::clone(&flowbox_ref);
-        //let flowbox_ref = flowbox_ref.lock().unwrap();
-        //let flowbox = flowbox_ref.deref();
-        // Run async code to get all required values for populating themes
-        adw::gio::spawn_blocking(move || {
-            let sender_ref = sender_ref.lock().unwrap();
-            let sender = sender_ref.deref();
-            let productpage_mutex = productpage_ref.lock().unwrap();
-            let productpage = productpage_mutex.deref();
-            //let productprops = productpage;
+    // Every new search bumps the generation, so late pages of an older query are dropped
+    let (sender, receiver) =
+        async_channel::unbounded::<(u32, String, std::result::Result<ProductCatalog, String>)>();
+    let generation = Rc::new(RefCell::new(0u32));
+    let loading = Rc::new(RefCell::new(false));
+    let loadeditems = Rc::new(RefCell::new((0i64, 0i64)));
 
-            let productcatalog: ProductCatalog = get_search_product_catalog(&productpage).unwrap();
-            //downloadthumbs(productcatalog.data.clone()).unwrap();
-            sender
-                .send_blocking(("firstload".to_string(), productcatalog))
-                .unwrap_or_default();
-        });
+    let fetch_page = {
+        let sender = sender.clone();
+        let loading = loading.clone();
+        let loadmorespinner = loadmorespinner.clone();
+        let loadmoreerrorbox = loadmoreerrorbox.clone();
+        move |generation: u32, message: &str, searchpageprops: SearchPageProps| {
+            *loading.borrow_mut() = true;
+            loadmorespinner.set_visible(message == "loadmore");
+            loadmoreerrorbox.set_visible(false);
+            let sender = sender.clone();
+            let message = message.to_string();
+            adw::gio::spawn_blocking(move || {
+                let productcatalog =
+                    get_search_product_catalog(&searchpageprops).map_err(|e| e.to_string());
+                sender
+                    .send_blocking((generation, message, productcatalog))
+                    .unwrap_or_default();
+            });
+        }
+    };
+
+    let start_search: Rc<dyn Fn()> = Rc::new({
+        let generation = generation.clone();
+        let searchpageprops = searchpageprops.clone();
+        let fetch_page = fetch_page.clone();
+        move || {
+            *generation.borrow_mut() += 1;
+            searchpageprops.borrow_mut().set_page(0);
+            let props = searchpageprops.borrow().clone();
+            fetch_page(*generation.borrow(), "firstload", props);
+        }
+    });
+
+    let search_changed_props = searchpageprops.clone();
+    let search_changed_start = start_search.clone();
+    searchentry.connect_search_changed(move |searchentry| {
+        search_changed_props
+            .borrow_mut()
+            .set_search_text(searchentry.text().to_string());
+        search_changed_start();
+    });
+
+    // Infinite scroll: fetch the next page once the bottom is reached. The page number
+    // only moves on once a page arrived, a failed one is asked for again on Retry.
+    let load_more = {
+        let generation = generation.clone();
+        let searchpageprops = searchpageprops.clone();
+        move || {
+            let mut props = searchpageprops.borrow().clone();
+            let nextpage = props.pageno + 1;
+            props.set_page(nextpage);
+            fetch_page(*generation.borrow(), "loadmore", props);
+        }
+    };
+    let edge_loading = loading.clone();
+    let edge_loadeditems = loadeditems.clone();
+    let edge_loadmoreerrorbox = loadmoreerrorbox.clone();
+    let edge_load_more = load_more.clone();
+    scrollwindow.connect_edge_reached(move |_, position| {
+        let (loaded, total) = *edge_loadeditems.borrow();
+        if position != PositionType::Bottom
+            || *edge_loading.borrow()
+            || edge_loadmoreerrorbox.is_visible()
+            || loaded >= total
+        {
+            return;
+        }
+        edge_load_more();
     });
+    loadmoreretrybutton.connect_clicked(move |_| load_more());
 
     // The main loop executes the asynchronous block
     let window: ApplicationWindow = window.clone();
+    let searchpageprops = searchpageprops.clone();
     glib::spawn_future_local({
         async move {
-            while let Ok((message, productcatalog)) = receiver.recv().await {
-                println!("Search Entry Changed Recv");
-                if message.eq("firstload") {
-                    flowboxrevealer_clone.set_reveal_child(false);
-                    while flowbox.first_child().is_some() {
-                        let child = flowbox.first_child().unwrap();
+            while let Ok((message_generation, message, productcatalog)) = receiver.recv().await {
+                if message_generation != *generation.borrow() {
+                    continue;
+                }
+                *loading.borrow_mut() = false;
+                loadmorespinner.set_visible(false);
+                let productcatalog = match productcatalog {
+                    Ok(productcatalog) => productcatalog,
+                    Err(e) => {
+                        eprintln!("Search failed : {}", e);
+                        if message.eq("loadmore") {
+                            loadmoreerrorlabel
+                                .set_label(&format!("Could not load more themes : {}", e));
+                            loadmoreerrorbox.set_visible(true);
+                        }
+                        continue;
+                    }
+                };
+                if message.eq("loadmore") {
+                    let mut searchpageprops = searchpageprops.borrow_mut();
+                    let nextpage = searchpageprops.pageno + 1;
+                    searchpageprops.set_page(nextpage);
+                } else if message.eq("firstload") {
+                    flowboxrevealer.set_reveal_child(false);
+                    while let Some(child) = flowbox.first_child() {
                         flowbox.remove(&child);
                     }
-                    while themecategory_loadingpage.first_child().is_some() {
-                        let child = themecategory_loadingpage.first_child().unwrap();
+                    while let Some(child) = themecategory_loadingpage.first_child() {
                         themecategory_loadingpage.remove(&child);
                     }
-                    for each_product in productcatalog.data {
-                        build_flowbox_for_page(&each_product, &flowbox, &window);
-                    }
-                    //themecategory_loadingpage.remove(&search_contentbox);
-
+                    *loadeditems.borrow_mut() = (0, productcatalog.totalitems);
                     themecategory_loadingpage.append(&searchcontentpage);
-                    flowboxrevealer.set_reveal_child(true);
+                    scrollwindow.vadjustment().set_value(0.0);
+                }
+                loadeditems.borrow_mut().0 += productcatalog.data.len() as i64;
+                // An empty page means the server has nothing more, whatever totalitems says
+                if productcatalog.data.is_empty() {
+                    let loaded = loadeditems.borrow().0;
+                    loadeditems.borrow_mut().1 = loaded;
+                }
+                for each_product in productcatalog.data {
+                    build_flowbox_for_page(&each_product, &flowbox, &window);
                 }
+                noresultslabel.set_visible(loadeditems.borrow().0 == 0);
+                flowboxrevealer.set_reveal_child(true);
             }
         }
     });
+
+    start_search
 }
 
 fn main() -> glib::ExitCode {
+    // `linuxthemestore sync <manifest.json>` converges the machine without opening a window
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("sync") {
+        return sync::run_cli(&args[2..]);
+    }
+
     // Initialize GTK
     adw::init().unwrap();
     load_custom_css();
 
     // Create a new application
-    let app = adw::Application::builder()
-        .application_id("io.github.debasish_patra_1987.linuxthemestore")
-        .build();
+    let app = adw::Application::builder().application_id(APP_ID).build();
 
     app.connect_activate(build_ui);
 
+    // Once per process, activate runs again for every window and notification click
+    app.connect_startup(|app| {
+        check_followed_authors(app);
+        let app = app.clone();
+        glib::timeout_add_seconds_local(FOLLOW_CHECK_INTERVAL, move || {
+            check_followed_authors(&app);
+            glib::ControlFlow::Continue
+        });
+    });
+
+    // Target of the notifications about new uploads from followed authors
+    let show_author = adw::gio::SimpleAction::new("show-author", Some(glib::VariantTy::STRING));
+    let action_app = app.clone();
+    show_author.connect_activate(move |_, personid| {
+        let personid = match personid.and_then(|p| p.get::<String>()) {
+            Some(personid) => personid,
+            None => return,
+        };
+        // Started by clicking the notification, open the main window first
+        if action_app.active_window().is_none() {
+            action_app.activate();
+        }
+        if let Some(window) = action_app
+            .active_window()
+            .and_then(|w| w.downcast::<ApplicationWindow>().ok())
+        {
+            show_author_page(&personid, &window);
+        }
+    });
+    app.add_action(&show_author);
+
+    // Targets of the notifications about finished installs
+    let show_installed = adw::gio::SimpleAction::new("show-installed", None);
+    let action_app = app.clone();
+    show_installed.connect_activate(move |_, _| {
+        if action_app.active_window().is_none() {
+            action_app.activate();
+        }
+        if let Some(window) = action_app.active_window() {
+            window.present();
+            if let Err(e) =
+                window.activate_action("win.show-page", Some(&"Installed".to_variant()))
+            {
+                eprintln!("Failed to show installed themes : {}", e);
+            }
+        }
+    });
+    app.add_action(&show_installed);
+
+    let show_queue = adw::gio::SimpleAction::new("show-queue", None);
+    let action_app = app.clone();
+    show_queue.connect_activate(move |_, _| {
+        if action_app.active_window().is_none() {
+            action_app.activate();
+        }
+        if let Some(window) = action_app.active_window() {
+            window.present();
+            if let Err(e) = window.activate_action("win.show-queue", None) {
+                eprintln!("Failed to show the downloads : {}", e);
+            }
+        }
+    });
+    app.add_action(&show_queue);
+
+    let resolve_conflict =
+        adw::gio::SimpleAction::new("resolve-conflict", glib::VariantTy::new("(us)").ok());
+    resolve_conflict.connect_activate(|_, target| {
+        if let Some((id, response)) = target.and_then(|t| t.get::<(u32, String)>()) {
+            queue::resolve_conflict(id, &response);
+        }
+    });
+    app.add_action(&resolve_conflict);
+
+    let apply_installed =
+        adw::gio::SimpleAction::new("apply-theme", glib::VariantTy::new("(ss)").ok());
+    apply_installed.connect_activate(|_, target| {
+        let (catalogid, directory) = match target.and_then(|t| t.get::<(String, String)>()) {
+            Some(target) => target,
+            None => return,
+        };
+        if let Err(e) = desktop::apply_theme(&Catalog::id_to_catalog(&catalogid), &directory) {
+            eprintln!("Failed to apply {} : {}", directory, e);
+        }
+    });
+    app.add_action(&apply_installed);
+
+    // Installs keep going after the window is closed, the app quits once the queue is done
+    let hold: RefCell<Option<adw::gio::ApplicationHoldGuard>> = RefCell::new(None);
+    let queue_app = app.clone();
+    queue::download_queue().connect_changed(move || {
+        let busy = queue::download_queue().is_busy();
+        let mut hold = hold.borrow_mut();
+        if busy && hold.is_none() {
+            *hold = Some(queue_app.hold());
+        } else if !busy {
+            *hold = None;
+        }
+        glib::ControlFlow::Continue
+    });
+    let queue_app = app.clone();
+    queue::download_queue().connect_job_outcome(move |job| {
+        notify_install_done(&queue_app, job);
+        glib::ControlFlow::Continue
+    });
+
     app.run()
 }
 
 fn build_ui(app: &adw::Application) {
+    settings::migrate_json_settings();
+    let settings = Settings::load();
+    adw::gio::spawn_blocking({
+        let settings = settings.clone();
+        move || {
+            if let Err(e) = archive_cache::trim_archive_cache(settings.archive_cache_limit) {
+                eprintln!("Failed to trim the download cache : {}", e);
+            }
+            let preview_cache = std::path::Path::new("/tmp/themeinstaller/cache");
+            if let Err(e) = storage::trim_cache(preview_cache, settings.preview_cache_limit) {
+                eprintln!("Failed to trim {} : {}", preview_cache.display(), e);
+            }
+        }
+    });
+
     // Header bar and view switcher
     let header_bar = adw::HeaderBar::new();
     let header_box = GtkBox::new(Orientation::Vertical, 10);
@@ -1746,13 +3903,59 @@ fn build_ui(app: &adw::Application) {
     let window = ApplicationWindow::builder()
         .application(app)
         .content(&header_box)
-        .default_width(1980)
-        .default_height(1080)
+        .default_width(settings.window_width)
+        .default_height(settings.window_height)
+        .maximized(settings.window_maximized)
         .build();
+    window.connect_close_request(|window| {
+        if queue::download_queue().is_busy()
+            && let Some(app) = window.application()
+        {
+            let notification = adw::gio::Notification::new("Installing in the Background");
+            notification.set_body(Some(
+                "Themes that are still downloading will finish, you get notified when they are done",
+            ));
+            app.send_notification(Some("install-background"), &notification);
+        }
+        let mut settings = Settings::load();
+        settings.window_maximized = window.is_maximized();
+        // The size to come back to after unmaximizing
+        if !settings.window_maximized {
+            (settings.window_width, settings.window_height) = window.default_size();
+        }
+        if let Err(e) = settings.save() {
+            eprintln!("Failed to save window size : {}", e);
+        }
+        glib::Propagation::Proceed
+    });
 
     let about_button = Button::from_icon_name("dialog-information-symbolic");
     header_bar.pack_end(&about_button);
 
+    let preferences_button = Button::builder()
+        .icon_name("preferences-system-symbolic")
+        .tooltip_text("Preferences")
+        .build();
+    header_bar.pack_end(&preferences_button);
+    let window_clone = window.clone();
+    preferences_button.connect_clicked(move |_| {
+        build_preferences_dialog().present(Some(&window_clone));
+    });
+
+    let profiles_button = Button::builder()
+        .icon_name("preferences-desktop-appearance-symbolic")
+        .tooltip_text("Theme Profiles")
+        .build();
+    header_bar.pack_end(&profiles_button);
+    let window_clone = window.clone();
+    profiles_button.connect_clicked(move |_| {
+        build_profiles_dialog(&window_clone).present(Some(&window_clone));
+    });
+
+    // Pending, running and failed installs from all product dialogs
+    let queuebutton = queue::build_queue_button();
+    header_bar.pack_end(&queuebutton);
+
     let window_clone = window.clone();
     about_button.connect_clicked(move |_| {
         let about_dialog = AboutDialog::builder()
@@ -1771,5 +3974,21 @@ fn build_ui(app: &adw::Application) {
         build_category_page(&view_stack, &outer_view_stack, &each_catalog_type, &window);
     }
     build_search_page(&view_stack, &outer_view_stack, &window);
+    build_favorites_page(&view_stack, &window);
+    build_installed_page(&view_stack);
+
+    // Lets app actions, like the ones of install notifications, switch pages
+    let show_page = adw::gio::SimpleAction::new("show-page", Some(glib::VariantTy::STRING));
+    let page_stack = view_stack.clone();
+    show_page.connect_activate(move |_, name| {
+        if let Some(name) = name.and_then(|name| name.get::<String>()) {
+            page_stack.set_visible_child_name(&name);
+        }
+    });
+    window.add_action(&show_page);
+    let show_queue = adw::gio::SimpleAction::new("show-queue", None);
+    show_queue.connect_activate(move |_, _| queuebutton.popup());
+    window.add_action(&show_queue);
+    view_stack.set_visible_child_name(settings.default_catalog.to_string());
     window.present();
 }
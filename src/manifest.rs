@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::installer::InstallScope;
+use crate::storage::{data_dir, load_json, save_json};
+use crate::{Catalog, Result};
+
+// One installed variant of a product
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstallRecord {
+    pub productid: i64,
+    pub name: String,
+    pub catalog: Catalog,
+    pub downloadname: String,
+    // Empty for installs recorded before checksums were tracked
+    #[serde(default)]
+    pub downloadmd5sum: String,
+    pub changed: String,
+    pub scope: InstallScope,
+    // Directory the archive was extracted into
+    pub location: String,
+    // Top level directories the archive created inside `location`
+    pub directories: Vec<String>,
+    pub installed: String,
+    // Validation problems found after extraction, empty for a complete install
+    #[serde(default)]
+    pub issues: Vec<String>,
+    // Directories that replaced an earlier version, which was kept as a backup
+    #[serde(default)]
+    pub backups: Vec<String>,
+    // The install those backups belong to, restored on rollback
+    #[serde(default)]
+    pub previous: Option<Box<InstallRecord>>,
+}
+
+impl InstallRecord {
+    pub fn is_complete(&self) -> bool {
+        self.issues.is_empty()
+    }
+    pub fn is_same_install(&self, other: &InstallRecord) -> bool {
+        self.productid == other.productid
+            && self.downloadname == other.downloadname
+            && self.scope == other.scope
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstallManifest {
+    pub records: Vec<InstallRecord>,
+}
+
+// Queued installs finish on worker threads at the same time, every read and
+// read-modify-write of the manifest goes through this
+static MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+
+impl InstallManifest {
+    fn path() -> PathBuf {
+        data_dir().join("installed.json")
+    }
+    pub fn load() -> InstallManifest {
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_json(&InstallManifest::path())
+    }
+    // Loads, changes and saves the manifest without another thread writing in between
+    pub fn update<T>(change: impl FnOnce(&mut InstallManifest) -> T) -> Result<T> {
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut manifest: InstallManifest = load_json(&InstallManifest::path());
+        let result = change(&mut manifest);
+        save_json(&InstallManifest::path(), &manifest)?;
+        Ok(result)
+    }
+    pub fn upsert(&mut self, record: InstallRecord) {
+        match self.records.iter_mut().find(|r| r.is_same_install(&record)) {
+            Some(existing) => *existing = record,
+            None => self.records.push(record),
+        }
+    }
+    pub fn remove(&mut self, record: &InstallRecord) {
+        self.records.retain(|r| !r.is_same_install(record));
+    }
+}
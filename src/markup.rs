@@ -0,0 +1,262 @@
+use adw::glib;
+
+// Links with any other scheme (javascript:, file:, data:) are shown as plain text
+const SAFE_URI_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+// Elements whose content is never shown
+const SKIPPED_ELEMENTS: [&str; 6] = [
+    "script", "style", "noscript", "iframe", "object", "template",
+];
+
+// Converts the formatting OCS descriptions use into Pango markup for a Label.
+// Unknown tags are dropped, their text is kept.
+pub fn html_to_markup(html: &str) -> String {
+    convert(html, true)
+}
+
+// Same conversion without any markup, for search, tooltips and other plain text
+pub fn html_to_text(html: &str) -> String {
+    convert(html, false)
+}
+
+pub fn is_safe_uri(uri: &str) -> bool {
+    match glib::Uri::peek_scheme(uri) {
+        Some(scheme) => SAFE_URI_SCHEMES.contains(&scheme.as_str()),
+        None => false,
+    }
+}
+
+fn convert(html: &str, with_markup: bool) -> String {
+    let mut output = Output {
+        text: String::new(),
+        open_tags: vec![],
+        with_markup,
+    };
+    // One entry per open list, the counter of an <ol> or None for <ul>
+    let mut lists: Vec<Option<u32>> = vec![];
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        output.push_text(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            rest = match rest.find("-->") {
+                Some(end) => &rest[end + 3..],
+                None => "",
+            };
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !closing && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            rest = skip_element(rest, &name);
+            continue;
+        }
+        match (name.as_str(), closing) {
+            ("b" | "strong", false) => output.open_tag("b", "<b>"),
+            ("i" | "em", false) => output.open_tag("i", "<i>"),
+            ("u", false) => output.open_tag("u", "<u>"),
+            ("a", false) => {
+                if let Some(href) = attribute(tag, "href")
+                    && is_safe_uri(&href)
+                {
+                    let opening = format!("<a href=\"{}\">", glib::markup_escape_text(&href));
+                    output.open_tag("a", &opening);
+                }
+            }
+            ("b" | "strong", true) => output.close_tag("b"),
+            ("i" | "em", true) => output.close_tag("i"),
+            ("u", true) => output.close_tag("u"),
+            ("a", true) => output.close_tag("a"),
+            ("br", _) => output.text.push('\n'),
+            ("p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => output.text.push('\n'),
+            ("ul", false) => lists.push(None),
+            ("ol", false) => lists.push(Some(0)),
+            ("ul" | "ol", true) => {
+                lists.pop();
+                output.text.push('\n');
+            }
+            ("li", false) => {
+                let indent = "  ".repeat(lists.len().saturating_sub(1));
+                match lists.last_mut() {
+                    Some(Some(counter)) => {
+                        *counter += 1;
+                        output.text.push_str(&format!("\n{}{}. ", indent, counter));
+                    }
+                    _ => output.text.push_str(&format!("\n{}• ", indent)),
+                }
+            }
+            // Images, forms and everything else
+            _ => {}
+        }
+    }
+    // An unterminated tag at the end is dropped
+    if !rest.starts_with('<') {
+        output.push_text(rest);
+    }
+    while let Some(name) = output.open_tags.pop() {
+        if with_markup {
+            output.text.push_str(&format!("</{}>", name));
+        }
+    }
+    tidy_lines(&output.text)
+}
+
+struct Output<'a> {
+    text: String,
+    // Pango tags currently open, closed again at the end so the markup always parses
+    open_tags: Vec<&'a str>,
+    with_markup: bool,
+}
+
+impl<'a> Output<'a> {
+    fn push_text(&mut self, html: &str) {
+        let text = decode_entities(html);
+        match self.with_markup {
+            true => self.text.push_str(&glib::markup_escape_text(&text)),
+            false => self.text.push_str(&text),
+        }
+    }
+
+    fn open_tag(&mut self, name: &'a str, opening: &str) {
+        if self.with_markup {
+            self.text.push_str(opening);
+        }
+        self.open_tags.push(name);
+    }
+
+    // Closes `name` and everything opened inside it, stray closing tags are ignored
+    fn close_tag(&mut self, name: &str) {
+        if !self.open_tags.contains(&name) {
+            return;
+        }
+        while let Some(open) = self.open_tags.pop() {
+            if self.with_markup {
+                self.text.push_str(&format!("</{}>", open));
+            }
+            if open == name {
+                break;
+            }
+        }
+    }
+}
+
+// Everything up to and including the matching closing tag
+fn skip_element<'a>(html: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{}", name);
+    match html.to_ascii_lowercase().find(&closing) {
+        Some(start) => match html[start..].find('>') {
+            Some(end) => &html[start + end + 1..],
+            None => "",
+        },
+        None => "",
+    }
+}
+
+// Value of an attribute, e.g. href="https://..." or href='...', with entities decoded
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lowercase_tag = tag.to_ascii_lowercase();
+    let start = lowercase_tag.find(&format!("{}=", name))? + name.len() + 1;
+    let value = &tag[start..];
+    let quote = value.chars().next()?;
+    let value = match quote {
+        '"' | '\'' => value[1..].split(quote).next()?,
+        _ => value.split(char::is_whitespace).next()?,
+    };
+    match value.trim().is_empty() {
+        true => None,
+        false => Some(decode_entities(value.trim())),
+    }
+}
+
+// Named entities seen in OCS descriptions plus all numeric ones. Unknown entities are kept as written.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match entity {
+            Some((c, length)) => {
+                decoded.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code).filter(|c| *c != '\0');
+    }
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "bull" => '•',
+        "middot" => '·',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "deg" => '°',
+        "times" => '×',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "rarr" => '→',
+        "larr" => '←',
+        _ => return None,
+    };
+    Some(c)
+}
+
+// Descriptions often have both <br> and raw newlines, keep at most one empty line in a row
+fn tidy_lines(text: &str) -> String {
+    let mut tidy = String::with_capacity(text.len());
+    let mut empty_lines = 0;
+    for line in text.replace("\r\n", "\n").split('\n') {
+        let line = line.trim_end();
+        match line.trim().is_empty() {
+            true => empty_lines += 1,
+            false => empty_lines = 0,
+        }
+        if empty_lines < 2 {
+            tidy.push_str(line);
+            tidy.push('\n');
+        }
+    }
+    tidy.trim().to_string()
+}
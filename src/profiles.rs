@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::desktop::{apply_theme, current_theme};
+use crate::gtk4_config::{apply_gtk4_theme, Gtk4ThemeState};
+use crate::installer::{install_theme, ConflictResolution};
+use crate::manifest::{InstallManifest, InstallRecord};
+use crate::settings::get_install_scope;
+use crate::storage::{data_dir, load_json, save_json};
+use crate::{get_product, Catalog, Result};
+
+// One themed part of the desktop, pinned to the store product and variant it came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileComponent {
+    pub catalog: Catalog,
+    pub productid: i64,
+    pub name: String,
+    pub downloadname: String,
+    // Theme directory name the desktop settings refer to
+    pub directory: String,
+}
+
+impl ProfileComponent {
+    // Installed copy of this component, if any
+    fn installed_dir(&self) -> Option<PathBuf> {
+        InstallManifest::load()
+            .records
+            .iter()
+            .filter(|r| r.productid == self.productid && r.downloadname == self.downloadname)
+            .map(|r| Path::new(&r.location).join(&self.directory))
+            .find(|dir| dir.is_dir())
+    }
+}
+
+// Also the export format, one profile per file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub components: Vec<ProfileComponent>,
+    pub created: String,
+}
+
+impl Profile {
+    // Records the themes the desktop uses right now. Themes that were not installed
+    // by the store can not be reinstalled elsewhere, they are returned as skipped.
+    pub fn capture(name: &str) -> (Profile, Vec<String>) {
+        let manifest = InstallManifest::load();
+        let mut components = vec![];
+        let mut skipped = vec![];
+        for catalog in Catalog::get_all_catalog_types() {
+            let directory = match current_theme(&catalog) {
+                Some(directory) => directory,
+                None => continue,
+            };
+            match find_record(&manifest, &catalog, &directory) {
+                Some(record) => components.push(ProfileComponent {
+                    catalog: catalog.clone(),
+                    productid: record.productid,
+                    name: record.name.clone(),
+                    downloadname: record.downloadname.clone(),
+                    directory,
+                }),
+                None => skipped.push(format!(
+                    "{} {} was not installed from the store",
+                    catalog.to_string(),
+                    directory
+                )),
+            }
+        }
+        let profile = Profile {
+            name: name.to_string(),
+            components,
+            created: chrono::Local::now().to_rfc3339(),
+        };
+        (profile, skipped)
+    }
+
+    // e.g. "Orchis (Gtk Themes), Papirus (Full Icon Themes)"
+    pub fn summary(&self) -> String {
+        self.components
+            .iter()
+            .map(|c| format!("{} ({})", c.directory, c.catalog.to_string()))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    // Suggested name of the export, profile names may contain anything
+    pub fn export_file_name(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| match c == '/' || c == '\\' || c.is_control() {
+                true => '-',
+                false => c,
+            })
+            .collect();
+        let name = name.trim().trim_start_matches('.');
+        match name.is_empty() {
+            true => String::from("profile.json"),
+            false => format!("{}.json", name),
+        }
+    }
+
+    pub fn export(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn import(path: &Path) -> Result<Profile> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+fn find_record<'a>(
+    manifest: &'a InstallManifest,
+    catalog: &Catalog,
+    directory: &str,
+) -> Option<&'a InstallRecord> {
+    manifest
+        .records
+        .iter()
+        .filter(|r| r.catalog == *catalog && r.directories.iter().any(|d| d == directory))
+        .find(|r| Path::new(&r.location).join(directory).is_dir())
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    fn path() -> PathBuf {
+        data_dir().join("profiles.json")
+    }
+    pub fn load() -> Profiles {
+        load_json(&Profiles::path())
+    }
+    pub fn save(&self) -> Result<()> {
+        save_json(&Profiles::path(), self)
+    }
+
+    // Profiles are keyed by name, saving under an existing name replaces it
+    pub fn upsert(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.profiles.retain(|p| p.name != name);
+    }
+}
+
+// Downloads and installs the components that are not on this machine yet,
+// in the install location configured for each catalog. Blocking, run off the main thread.
+pub fn install_missing_components(profile: &Profile) -> Vec<std::result::Result<String, String>> {
+    let mut results = vec![];
+    for component in &profile.components {
+        if component.installed_dir().is_some() {
+            continue;
+        }
+        let result = install_component(component)
+            .map(|_| format!("Installed {}", component.name))
+            .map_err(|e| format!("Failed to install {} : {}", component.name, e));
+        results.push(result);
+    }
+    results
+}
+
+fn install_component(component: &ProfileComponent) -> Result<()> {
+    let product = get_product(component.productid)?;
+    let downloaddetail = match product
+        .downloaddetails
+        .iter()
+        .find(|d| d.downloadname == component.downloadname)
+    {
+        Some(downloaddetail) => downloaddetail.clone(),
+        None => {
+            return Err(format!("{} is no longer offered", component.downloadname).into());
+        }
+    };
+    let scope = get_install_scope(&component.catalog);
+    let outcome = install_theme(
+        &product,
+        &downloaddetail,
+        &component.catalog,
+        &scope,
+        &ConflictResolution::Replace,
+    )?;
+    if !outcome.record.directories.contains(&component.directory) {
+        return Err(format!(
+            "{} did not contain {}",
+            component.downloadname, component.directory
+        )
+        .into());
+    }
+    Ok(())
+}
+
+// Switches every component on the desktop, returns what could not be applied.
+// Uses GSettings, so it has to run on the main thread after the installs finished.
+pub fn apply_profile(profile: &Profile) -> Vec<String> {
+    let mut errors = vec![];
+    for component in &profile.components {
+        let theme_dir = match component.installed_dir() {
+            Some(theme_dir) => theme_dir,
+            None => {
+                errors.push(format!("{} is not installed", component.name));
+                continue;
+            }
+        };
+        if let Err(e) = apply_theme(&component.catalog, &component.directory) {
+            errors.push(format!("Failed to apply {} : {}", component.name, e));
+            continue;
+        }
+        // Keep libadwaita apps in line when the user opted into theming them
+        if component.catalog == Catalog::Gtk4Themes
+            && let Some(state) = Gtk4ThemeState::load()
+            && let Err(e) = apply_gtk4_theme(&theme_dir, &state.mode)
+        {
+            errors.push(format!(
+                "Failed to apply {} to libadwaita apps : {}",
+                component.name, e
+            ));
+        }
+    }
+    errors
+}
@@ -0,0 +1,460 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use adw::gio;
+use adw::glib;
+use adw::glib::object::IsA;
+use adw::glib::prelude::ObjectExt;
+use adw::gtk::prelude::{BoxExt, ButtonExt, WidgetExt};
+use adw::gtk::{Align, Box as GtkBox, Button, Image, ListBox, Orientation, PolicyType, Widget};
+use adw::prelude::{ActionRowExt, AdwDialogExt, AlertDialogExt, AlertDialogExtManual};
+use adw::ActionRow;
+use gtk4::{MenuButton, Popover, ScrolledWindow, SelectionMode};
+
+use crate::installer::{
+    install_theme, renamed_directory, Conflict, ConflictError, ConflictResolution, InstallOutcome,
+    InstallScope,
+};
+use crate::{Catalog, DownloadDetail, Product};
+
+// Downloads and installs running at the same time, the rest waits its turn
+const MAX_ACTIVE_JOBS: usize = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    Pending,
+    Active,
+    Finished(Box<InstallOutcome>),
+    Failed(String),
+    // Stopped before changing anything until the user picks a ConflictResolution
+    Conflict(Vec<Conflict>),
+    Cancelled,
+}
+
+impl JobState {
+    pub fn to_string(&self) -> &str {
+        match self {
+            JobState::Pending => "Waiting",
+            JobState::Active => "Installing",
+            JobState::Finished(_) => "Installed",
+            JobState::Failed(_) => "Failed",
+            JobState::Conflict(_) => "Already installed",
+            JobState::Cancelled => "Cancelled",
+        }
+    }
+    fn is_done(&self) -> bool {
+        matches!(
+            self,
+            JobState::Finished(_) | JobState::Failed(_) | JobState::Cancelled
+        )
+    }
+}
+
+type JobListener = Rc<dyn Fn(&Job)>;
+// Queue wide listeners return Break once the widgets they update are gone and are dropped then
+type ChangeListener = Rc<dyn Fn() -> glib::ControlFlow>;
+type OutcomeListener = Rc<dyn Fn(&Job) -> glib::ControlFlow>;
+
+#[derive(Clone)]
+pub struct Job {
+    pub id: u32,
+    pub product: Product,
+    pub variant: DownloadDetail,
+    pub catalog: Catalog,
+    pub scope: InstallScope,
+    pub resolution: ConflictResolution,
+    pub state: JobState,
+    // Told about every state change of this job, e.g. to update the variant row that queued it
+    on_state: JobListener,
+}
+
+impl Job {
+    fn is_same_variant(&self, product: &Product, variant: &DownloadDetail) -> bool {
+        self.product.id == product.id && self.variant.downloadname == variant.downloadname
+    }
+}
+
+// Every install started from a product dialog goes through this queue. It lives on
+// the main thread, only install_theme itself runs on a worker.
+#[derive(Default)]
+pub struct DownloadQueue {
+    jobs: RefCell<Vec<Job>>,
+    next_id: Cell<u32>,
+    listeners: RefCell<Vec<ChangeListener>>,
+    outcome_listeners: RefCell<Vec<OutcomeListener>>,
+}
+
+thread_local! {
+    static QUEUE: Rc<DownloadQueue> = Rc::new(DownloadQueue::default());
+}
+
+pub fn download_queue() -> Rc<DownloadQueue> {
+    QUEUE.with(Rc::clone)
+}
+
+impl DownloadQueue {
+    // Queues a variant unless it is already waiting or installing. A failed
+    // attempt at the same variant is replaced by the new one.
+    pub fn enqueue(
+        self: &Rc<Self>,
+        product: &Product,
+        variant: &DownloadDetail,
+        catalog: &Catalog,
+        scope: &InstallScope,
+        on_state: impl Fn(&Job) + 'static,
+    ) {
+        let id = {
+            let mut jobs = self.jobs.borrow_mut();
+            if jobs
+                .iter()
+                .any(|job| job.is_same_variant(product, variant) && !job.state.is_done())
+            {
+                return;
+            }
+            jobs.retain(|job| {
+                !(job.is_same_variant(product, variant) && matches!(job.state, JobState::Failed(_)))
+            });
+            let id = self.next_id.get();
+            self.next_id.set(id + 1);
+            jobs.push(Job {
+                id,
+                product: product.clone(),
+                variant: variant.clone(),
+                catalog: catalog.clone(),
+                scope: scope.clone(),
+                resolution: ConflictResolution::Ask,
+                state: JobState::Pending,
+                on_state: Rc::new(on_state),
+            });
+            id
+        };
+        self.set_state(id, JobState::Pending);
+        self.start_pending();
+    }
+
+    pub fn retry(self: &Rc<Self>, id: u32) {
+        if let Some(job) = self.jobs.borrow().iter().find(|job| job.id == id)
+            && !matches!(job.state, JobState::Failed(_))
+        {
+            return;
+        }
+        self.set_state(id, JobState::Pending);
+        self.start_pending();
+    }
+
+    // Continues a job that stopped at a conflict
+    pub fn resolve(self: &Rc<Self>, id: u32, resolution: ConflictResolution) {
+        {
+            let mut jobs = self.jobs.borrow_mut();
+            match jobs.iter_mut().find(|job| job.id == id) {
+                Some(job) if matches!(job.state, JobState::Conflict(_)) => {
+                    job.resolution = resolution
+                }
+                _ => return,
+            }
+        }
+        self.set_state(id, JobState::Pending);
+        self.start_pending();
+    }
+
+    // Drops a job that stopped at a conflict, its row goes back to how it was before
+    pub fn cancel(&self, id: u32) {
+        if !self
+            .jobs
+            .borrow()
+            .iter()
+            .any(|job| job.id == id && matches!(job.state, JobState::Conflict(_)))
+        {
+            return;
+        }
+        self.set_state(id, JobState::Cancelled);
+        self.jobs.borrow_mut().retain(|job| job.id != id);
+        self.changed();
+    }
+
+    pub fn clear_finished(&self) {
+        self.jobs
+            .borrow_mut()
+            .retain(|job| !matches!(job.state, JobState::Finished(_)));
+        self.changed();
+    }
+
+    pub fn jobs(&self) -> Vec<Job> {
+        self.jobs.borrow().clone()
+    }
+
+    // Jobs still waiting, running or waiting for a decision about a conflict
+    pub fn is_busy(&self) -> bool {
+        self.jobs.borrow().iter().any(|job| !job.state.is_done())
+    }
+
+    // Called on the main thread after every change to the list or a job's state
+    pub fn connect_changed(&self, listener: impl Fn() -> glib::ControlFlow + 'static) {
+        self.listeners.borrow_mut().push(Rc::new(listener));
+    }
+
+    // Called when a job finished, failed or stopped at a conflict, after its own state callback
+    pub fn connect_job_outcome(&self, listener: impl Fn(&Job) -> glib::ControlFlow + 'static) {
+        self.outcome_listeners.borrow_mut().push(Rc::new(listener));
+    }
+
+    fn changed(&self) {
+        // Listeners may read the queue, so none of it is borrowed while they run
+        let listeners = self.listeners.borrow().clone();
+        for listener in listeners {
+            if listener().is_break() {
+                self.listeners
+                    .borrow_mut()
+                    .retain(|l| !Rc::ptr_eq(l, &listener));
+            }
+        }
+    }
+
+    fn set_state(&self, id: u32, state: JobState) {
+        let job = {
+            let mut jobs = self.jobs.borrow_mut();
+            match jobs.iter_mut().find(|job| job.id == id) {
+                Some(job) => {
+                    job.state = state.clone();
+                    job.clone()
+                }
+                None => return,
+            }
+        };
+        (job.on_state)(&job);
+        if matches!(
+            state,
+            JobState::Finished(_) | JobState::Failed(_) | JobState::Conflict(_)
+        ) {
+            let outcome_listeners = self.outcome_listeners.borrow().clone();
+            for listener in outcome_listeners {
+                if listener(&job).is_break() {
+                    self.outcome_listeners
+                        .borrow_mut()
+                        .retain(|l| !Rc::ptr_eq(l, &listener));
+                }
+            }
+        }
+        self.changed();
+    }
+
+    fn start_pending(self: &Rc<Self>) {
+        loop {
+            let job = {
+                let jobs = self.jobs.borrow();
+                let active = jobs
+                    .iter()
+                    .filter(|job| job.state == JobState::Active)
+                    .count();
+                if active >= MAX_ACTIVE_JOBS {
+                    return;
+                }
+                match jobs.iter().find(|job| job.state == JobState::Pending) {
+                    Some(job) => job.clone(),
+                    None => return,
+                }
+            };
+            self.set_state(job.id, JobState::Active);
+
+            let (sender, receiver) = async_channel::bounded::<JobState>(1);
+            // The state callback holds widgets, only the install inputs go to the worker
+            let (product, variant, catalog, scope, resolution) = (
+                job.product.clone(),
+                job.variant.clone(),
+                job.catalog.clone(),
+                job.scope.clone(),
+                job.resolution.clone(),
+            );
+            gio::spawn_blocking(move || {
+                let state = match install_theme(&product, &variant, &catalog, &scope, &resolution) {
+                    Ok(outcome) => JobState::Finished(Box::new(outcome)),
+                    Err(e) => match e.downcast::<ConflictError>() {
+                        Ok(conflict) => JobState::Conflict(conflict.0),
+                        Err(e) => {
+                            eprintln!("Install of {} failed : {}", variant.downloadname, e);
+                            JobState::Failed(e.to_string())
+                        }
+                    },
+                };
+                sender.send_blocking(state).unwrap_or_default();
+            });
+            let queue = self.clone();
+            glib::spawn_future_local(async move {
+                let state = match receiver.recv().await {
+                    Ok(state) => state,
+                    Err(e) => JobState::Failed(e.to_string()),
+                };
+                queue.set_state(job.id, state);
+                queue.start_pending();
+            });
+        }
+    }
+}
+
+// Header button with the queue panel, only shown while the queue has jobs
+pub fn build_queue_button() -> MenuButton {
+    let joblist = ListBox::builder()
+        .selection_mode(SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
+        .build();
+    let clearbutton = Button::builder()
+        .label("Clear Finished")
+        .halign(Align::End)
+        .css_classes(vec!["flat"])
+        .build();
+    let panel = GtkBox::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(6)
+        .margin_end(6)
+        .width_request(380)
+        .build();
+    panel.append(
+        &ScrolledWindow::builder()
+            .child(&joblist)
+            .hscrollbar_policy(PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(420)
+            .build(),
+    );
+    panel.append(&clearbutton);
+
+    let queuebutton = MenuButton::builder()
+        .icon_name("folder-download-symbolic")
+        .tooltip_text("Downloads")
+        .popover(&Popover::builder().child(&panel).build())
+        .visible(false)
+        .build();
+
+    let queue = download_queue();
+    clearbutton.connect_clicked({
+        let queue = queue.clone();
+        move |_| queue.clear_finished()
+    });
+    // Weak, the queue outlives the window this button is in
+    let update = {
+        let queue = queue.clone();
+        let joblist = joblist.downgrade();
+        let queuebutton = queuebutton.downgrade();
+        let clearbutton = clearbutton.downgrade();
+        move || {
+            let (Some(joblist), Some(queuebutton), Some(clearbutton)) = (
+                joblist.upgrade(),
+                queuebutton.upgrade(),
+                clearbutton.upgrade(),
+            ) else {
+                return glib::ControlFlow::Break;
+            };
+            while let Some(child) = joblist.first_child() {
+                joblist.remove(&child);
+            }
+            let jobs = queue.jobs();
+            for job in &jobs {
+                joblist.append(&build_job_row(job, &queue));
+            }
+            queuebutton.set_visible(!jobs.is_empty());
+            match queue.is_busy() {
+                true => queuebutton.add_css_class("accent"),
+                false => queuebutton.remove_css_class("accent"),
+            }
+            clearbutton.set_sensitive(
+                jobs.iter()
+                    .any(|job| matches!(job.state, JobState::Finished(_))),
+            );
+            glib::ControlFlow::Continue
+        }
+    };
+    update();
+    queue.connect_changed(update);
+    queuebutton
+}
+
+fn build_job_row(job: &Job, queue: &Rc<DownloadQueue>) -> ActionRow {
+    let row = ActionRow::builder()
+        .title(glib::markup_escape_text(&job.product.name))
+        .subtitle(format!(
+            "{} · {}",
+            glib::markup_escape_text(&job.variant.downloadname),
+            job.state.to_string()
+        ))
+        .build();
+    let icon = match &job.state {
+        JobState::Pending => "document-open-recent-symbolic",
+        JobState::Active => "folder-download-symbolic",
+        JobState::Finished(_) => "emblem-ok-symbolic",
+        JobState::Failed(_) => "dialog-error-symbolic",
+        JobState::Conflict(_) => "dialog-warning-symbolic",
+        JobState::Cancelled => "process-stop-symbolic",
+    };
+    row.add_prefix(&Image::from_icon_name(icon));
+    if let JobState::Failed(e) = &job.state {
+        row.set_tooltip_text(Some(e));
+        row.add_css_class("error");
+        let retrybutton = Button::builder()
+            .icon_name("view-refresh-symbolic")
+            .tooltip_text("Retry")
+            .valign(Align::Center)
+            .css_classes(vec!["flat"])
+            .build();
+        let queue = queue.clone();
+        let id = job.id;
+        retrybutton.connect_clicked(move |_| queue.retry(id));
+        row.add_suffix(&retrybutton);
+    }
+    if matches!(job.state, JobState::Conflict(_)) {
+        row.add_css_class("warning");
+        let resolvebutton = Button::builder()
+            .label("Resolve")
+            .valign(Align::Center)
+            .build();
+        let job = job.clone();
+        resolvebutton
+            .connect_clicked(move |resolvebutton| show_conflict_dialog(&job, resolvebutton));
+        row.add_suffix(&resolvebutton);
+    }
+    row
+}
+
+// Asks what to do about directories an install would overwrite
+pub fn show_conflict_dialog(job: &Job, parent: &impl IsA<Widget>) {
+    let conflicts = match &job.state {
+        JobState::Conflict(conflicts) => conflicts,
+        _ => return,
+    };
+    let described: Vec<String> = conflicts.iter().map(|c| c.describe()).collect();
+    let renamed: Vec<String> = conflicts
+        .iter()
+        .map(|c| renamed_directory(&c.directory, &job.product))
+        .collect();
+    let body = format!(
+        "Installing {} would overwrite:\n\n{}\n\nReplacing keeps the old files as a backup that can be rolled back from the Installed page. Installing renamed adds {} instead.",
+        job.product.name,
+        described.join("\n"),
+        renamed.join(", ")
+    );
+    let alert = adw::AlertDialog::new(Some("Theme Already Installed"), Some(&body));
+    alert.add_responses(&[
+        ("cancel", "Cancel"),
+        ("rename", "Install Renamed"),
+        ("replace", "Replace"),
+    ]);
+    alert.set_response_appearance("replace", adw::ResponseAppearance::Destructive);
+    alert.set_default_response(Some("rename"));
+    alert.set_close_response("cancel");
+    let id = job.id;
+    alert.connect_response(None, move |_, response| resolve_conflict(id, response));
+    alert.present(Some(parent));
+}
+
+// Answers a conflict with "replace", "rename" or anything else to cancel, from the
+// dialog or the buttons of the conflict notification
+pub fn resolve_conflict(id: u32, response: &str) {
+    let queue = download_queue();
+    match response {
+        "replace" => queue.resolve(id, ConflictResolution::Replace),
+        "rename" => queue.resolve(id, ConflictResolution::Rename),
+        _ => queue.cancel(id),
+    }
+}
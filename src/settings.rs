@@ -0,0 +1,192 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use adw::gio;
+use adw::gio::prelude::SettingsExt;
+use adw::glib::prelude::ToVariant;
+
+use crate::desktop::lookup_settings;
+use crate::installer::InstallScope;
+use crate::storage::{config_dir, load_json, save_json};
+use crate::{Catalog, Provider, Result, SortType};
+
+pub const APP_ID: &str = "io.github.debasish_patra_1987.linuxthemestore";
+
+// Stored in GSettings when the schema is installed, in ~/.config/linuxthemestore/settings.json
+// when running from a source checkout. Defaults match the schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub page_size: u8,
+    // Milliseconds
+    pub search_delay: u32,
+    pub default_catalog: Catalog,
+    pub default_sort: SortType,
+    // Megabytes, 0 keeps everything
+    pub archive_cache_limit: u32,
+    pub preview_cache_limit: u32,
+    pub provider: Provider,
+    pub default_install_scope: InstallScope,
+    // Keyed by Catalog::get_id(), overrides default_install_scope
+    pub install_scopes: HashMap<String, InstallScope>,
+    pub auto_apply: bool,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub window_maximized: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            page_size: 10,
+            search_delay: 500,
+            default_catalog: Catalog::Gtk4Themes,
+            default_sort: SortType::Latest,
+            archive_cache_limit: 500,
+            preview_cache_limit: 200,
+            provider: Provider::Pling,
+            default_install_scope: InstallScope::UserXdg,
+            install_scopes: HashMap::new(),
+            auto_apply: false,
+            window_width: 1980,
+            window_height: 1080,
+            window_maximized: false,
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> PathBuf {
+        config_dir().join("settings.json")
+    }
+    pub fn load() -> Settings {
+        match lookup_settings(APP_ID) {
+            Some(gsettings) => Settings::from_gsettings(&gsettings),
+            None => load_json(&Settings::path()),
+        }
+    }
+    pub fn save(&self) -> Result<()> {
+        match lookup_settings(APP_ID) {
+            Some(gsettings) => self.to_gsettings(&gsettings),
+            None => save_json(&Settings::path(), self),
+        }
+    }
+
+    fn from_gsettings(gsettings: &gio::Settings) -> Settings {
+        let defaults = Settings::default();
+        let install_scopes: HashMap<String, String> =
+            gsettings.value("install-scopes").get().unwrap_or_default();
+        Settings {
+            page_size: gsettings.int("page-size").clamp(1, u8::MAX.into()) as u8,
+            search_delay: gsettings.uint("search-delay"),
+            default_catalog: from_nick(
+                &gsettings.string("default-catalog"),
+                defaults.default_catalog,
+            ),
+            default_sort: from_nick(&gsettings.string("default-sort"), defaults.default_sort),
+            archive_cache_limit: gsettings.uint("archive-cache-limit"),
+            preview_cache_limit: gsettings.uint("preview-cache-limit"),
+            provider: from_nick(&gsettings.string("provider"), defaults.provider),
+            default_install_scope: from_nick(
+                &gsettings.string("default-install-scope"),
+                defaults.default_install_scope,
+            ),
+            install_scopes: install_scopes
+                .into_iter()
+                .map(|(catalog, scope)| (catalog, from_nick(&scope, InstallScope::default())))
+                .collect(),
+            auto_apply: gsettings.boolean("auto-apply"),
+            window_width: gsettings.int("window-width"),
+            window_height: gsettings.int("window-height"),
+            window_maximized: gsettings.boolean("window-maximized"),
+        }
+    }
+
+    fn to_gsettings(&self, gsettings: &gio::Settings) -> Result<()> {
+        let install_scopes: HashMap<String, String> = self
+            .install_scopes
+            .iter()
+            .map(|(catalog, scope)| (catalog.clone(), to_nick(scope)))
+            .collect();
+        // Written as one change so listeners never see half the settings
+        gsettings.delay();
+        let result = (|| -> Result<()> {
+            gsettings.set_int("page-size", self.page_size.into())?;
+            gsettings.set_uint("search-delay", self.search_delay)?;
+            gsettings.set_string("default-catalog", &to_nick(&self.default_catalog))?;
+            gsettings.set_string("default-sort", &to_nick(&self.default_sort))?;
+            gsettings.set_uint("archive-cache-limit", self.archive_cache_limit)?;
+            gsettings.set_uint("preview-cache-limit", self.preview_cache_limit)?;
+            gsettings.set_string("provider", &to_nick(&self.provider))?;
+            gsettings.set_string(
+                "default-install-scope",
+                &to_nick(&self.default_install_scope),
+            )?;
+            gsettings.set_value("install-scopes", &install_scopes.to_variant())?;
+            gsettings.set_boolean("auto-apply", self.auto_apply)?;
+            gsettings.set_int("window-width", self.window_width)?;
+            gsettings.set_int("window-height", self.window_height)?;
+            gsettings.set_boolean("window-maximized", self.window_maximized)?;
+            Ok(())
+        })();
+        match result {
+            Ok(_) => gsettings.apply(),
+            Err(_) => gsettings.revert(),
+        }
+        result
+    }
+}
+
+// Enum values are stored by their serde name, e.g. "Gtk4Themes" or "pling"
+fn to_nick<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(nick)) => nick,
+        _ => String::new(),
+    }
+}
+
+fn from_nick<T: DeserializeOwned>(nick: &str, fallback: T) -> T {
+    serde_json::from_value(serde_json::Value::String(nick.to_string())).unwrap_or(fallback)
+}
+
+// Moves settings.json into GSettings the first time the schema is available
+pub fn migrate_json_settings() {
+    let path = Settings::path();
+    if !path.exists() || lookup_settings(APP_ID).is_none() {
+        return;
+    }
+    let settings: Settings = load_json(&path);
+    match settings.save() {
+        Ok(_) => {
+            let _ = fs::rename(&path, path.with_extension("json.migrated"));
+        }
+        Err(e) => eprintln!("Failed to migrate {} : {}", path.display(), e),
+    }
+}
+
+pub fn get_install_scope(catalog: &Catalog) -> InstallScope {
+    let settings = Settings::load();
+    settings
+        .install_scopes
+        .get(catalog.get_id())
+        .cloned()
+        .unwrap_or(settings.default_install_scope)
+}
+
+// Loads, changes and saves in one go, for settings edited one at a time
+pub fn update_settings(change: impl FnOnce(&mut Settings)) -> Result<()> {
+    let mut settings = Settings::load();
+    change(&mut settings);
+    settings.save()
+}
+
+pub fn set_install_scope(catalog: &Catalog, scope: &InstallScope) -> Result<()> {
+    update_settings(|settings| {
+        settings
+            .install_scopes
+            .insert(catalog.get_id().to_string(), scope.clone());
+    })
+}
@@ -0,0 +1,122 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use adw::gio;
+use adw::gio::prelude::FileExt;
+use adw::glib;
+
+use crate::Result;
+
+const APP_DIR_NAME: &str = "linuxthemestore";
+
+// ~/.local/share/linuxthemestore
+pub fn data_dir() -> PathBuf {
+    glib::user_data_dir().join(APP_DIR_NAME)
+}
+
+// ~/.config/linuxthemestore
+pub fn config_dir() -> PathBuf {
+    glib::user_config_dir().join(APP_DIR_NAME)
+}
+
+// ~/.cache/linuxthemestore
+pub fn cache_dir() -> PathBuf {
+    glib::user_cache_dir().join(APP_DIR_NAME)
+}
+
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable {} : {}", path.display(), e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Write next to the target and rename so a crash never leaves half a file behind
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// Deletes the least recently written files under `dir` until it holds at most `limit_mb`.
+// A limit of 0 keeps everything.
+pub fn trim_cache(dir: &Path, limit_mb: u32) -> Result<()> {
+    if limit_mb == 0 || !dir.is_dir() {
+        return Ok(());
+    }
+    let mut files = vec![];
+    collect_files(dir, &mut files)?;
+    let limit = u64::from(limit_mb) * 1024 * 1024;
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort_by_key(|(_, _, modified)| *modified);
+    for (path, size, _) in files {
+        if total <= limit {
+            break;
+        }
+        fs::remove_file(&path)?;
+        total -= size;
+    }
+    Ok(())
+}
+
+// Total size of the files under `dir`, 0 when it does not exist
+pub fn dir_size(dir: &Path) -> u64 {
+    let mut files = vec![];
+    match collect_files(dir, &mut files) {
+        Ok(()) => files.iter().map(|(_, size, _)| size).sum(),
+        Err(_) => 0,
+    }
+}
+
+// Sizes as GNOME shows them, e.g. "3.5 MB". The store reports 0 for files it never measured.
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0 => String::from("Unknown size"),
+        _ => glib::format_size(bytes).to_string(),
+    }
+}
+
+// Id and free bytes of the filesystem `path` is on, or will be on once it is created
+pub fn free_space(path: &Path) -> Result<(String, u64)> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Path::new("/"));
+    let file = gio::File::for_path(existing);
+    let id = file
+        .query_info(
+            gio::FILE_ATTRIBUTE_ID_FILESYSTEM,
+            gio::FileQueryInfoFlags::NONE,
+            gio::Cancellable::NONE,
+        )?
+        .attribute_string(gio::FILE_ATTRIBUTE_ID_FILESYSTEM)
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+    let free = file
+        .query_filesystem_info(gio::FILE_ATTRIBUTE_FILESYSTEM_FREE, gio::Cancellable::NONE)?
+        .attribute_uint64(gio::FILE_ATTRIBUTE_FILESYSTEM_FREE);
+    Ok((id, free))
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<(PathBuf, u64, SystemTime)>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if metadata.is_file() {
+            files.push((entry.path(), metadata.len(), metadata.modified()?));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,450 @@
+use serde::Deserialize;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use adw::gio;
+use adw::glib;
+
+use crate::desktop::{apply_theme, current_theme};
+use crate::installer::{
+    find_store_product, install_theme, uninstall_theme, ConflictResolution, InstallScope,
+};
+use crate::manifest::{InstallManifest, InstallRecord};
+use crate::settings::get_install_scope;
+use crate::{get_product, Catalog, DownloadDetail, Product, Provider, Result};
+
+// Desired themes for a machine, meant to live in dotfiles. For example
+// {"prune": true, "themes": [{"name": "Papirus", "catalog": "FullIconThemes",
+//   "variant": "papirus-icon-theme-*.tar.gz", "directory": "Papirus-Dark", "apply": true}]}
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SyncManifest {
+    pub themes: Vec<SyncEntry>,
+    // Removes store installs the manifest does not list
+    pub prune: bool,
+}
+
+impl SyncManifest {
+    pub fn load(path: &Path) -> Result<SyncManifest> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+// One theme of the manifest, found by productid or else by name
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncEntry {
+    #[serde(default)]
+    pub provider: Provider,
+    pub productid: Option<i64>,
+    pub name: Option<String>,
+    pub catalog: Catalog,
+    // Download file name, * and ? wildcards allowed. Required when a product has several files.
+    pub variant: Option<String>,
+    // Theme directory to apply when the archive holds several, the first one otherwise
+    pub directory: Option<String>,
+    #[serde(default)]
+    pub apply: bool,
+    // Pins, the install is refused when the store no longer offers this version
+    pub changed: Option<String>,
+    pub md5: Option<String>,
+    // Install location configured in the app when not set
+    pub scope: Option<InstallScope>,
+}
+
+impl SyncEntry {
+    fn label(&self) -> String {
+        match (&self.productid, &self.name) {
+            (Some(productid), _) => format!("{} {}", self.provider.to_string(), productid),
+            (None, Some(name)) => format!("{} {}", self.provider.to_string(), name),
+            (None, None) => String::from("Entry without productid or name"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncAction {
+    Install(Box<Product>, DownloadDetail),
+    Update(Box<Product>, DownloadDetail, InstallRecord),
+    Keep(InstallRecord),
+    Remove(InstallRecord),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncStep {
+    pub catalog: Catalog,
+    pub scope: InstallScope,
+    pub action: SyncAction,
+    pub apply: bool,
+    pub directory: Option<String>,
+}
+
+impl SyncStep {
+    pub fn describe(&self) -> String {
+        let description = match &self.action {
+            SyncAction::Install(product, downloaddetail) => format!(
+                "install  {} ({}, changed {})",
+                product.name, downloaddetail.downloadname, product.changed
+            ),
+            SyncAction::Update(product, downloaddetail, record) => format!(
+                "update   {} ({}, changed {} -> {})",
+                product.name, downloaddetail.downloadname, record.changed, product.changed
+            ),
+            SyncAction::Keep(record) => {
+                format!("keep     {} ({})", record.name, record.downloadname)
+            }
+            SyncAction::Remove(record) => format!(
+                "remove   {} ({} from {})",
+                record.name,
+                record.directories.join(", "),
+                record.location
+            ),
+        };
+        let mut description = format!(
+            "{} [{}, {}]",
+            description,
+            self.catalog.to_string(),
+            self.scope.to_string()
+        );
+        if self.apply {
+            description.push_str(" + apply");
+        }
+        description
+    }
+
+    fn is_change(&self) -> bool {
+        match &self.action {
+            SyncAction::Keep(record) => {
+                self.apply && current_theme(&self.catalog) != Some(apply_directory(self, record))
+            }
+            _ => true,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct SyncPlan {
+    pub steps: Vec<SyncStep>,
+    // Entries that could not be resolved, nothing is executed while there are any
+    pub errors: Vec<String>,
+}
+
+impl SyncPlan {
+    pub fn has_changes(&self) -> bool {
+        self.steps.iter().any(|s| s.is_change())
+    }
+}
+
+// Compares the manifest with the store and what is installed. Blocking, only reads.
+pub fn plan_sync(syncmanifest: &SyncManifest) -> SyncPlan {
+    let installmanifest = InstallManifest::load();
+    let mut plan = SyncPlan::default();
+    let mut wanted: Vec<InstallRecord> = vec![];
+    for entry in &syncmanifest.themes {
+        match plan_entry(entry, &installmanifest) {
+            Ok(step) => {
+                match &step.action {
+                    SyncAction::Update(_, _, record) | SyncAction::Keep(record) => {
+                        wanted.push(record.clone())
+                    }
+                    _ => {}
+                }
+                plan.steps.push(step);
+            }
+            Err(e) => plan.errors.push(format!("{} : {}", entry.label(), e)),
+        }
+    }
+    if syncmanifest.prune {
+        for record in &installmanifest.records {
+            if !wanted.iter().any(|w| w.is_same_install(record)) {
+                plan.steps.push(SyncStep {
+                    catalog: record.catalog.clone(),
+                    scope: record.scope.clone(),
+                    action: SyncAction::Remove(record.clone()),
+                    apply: false,
+                    directory: None,
+                });
+            }
+        }
+    }
+    plan
+}
+
+fn plan_entry(entry: &SyncEntry, installmanifest: &InstallManifest) -> Result<SyncStep> {
+    let product = match (&entry.productid, &entry.name) {
+        (Some(productid), _) => get_product(*productid)?,
+        (None, Some(name)) => match find_store_product(name, &entry.catalog)? {
+            Some(product) => product,
+            None => return Err(format!("not found in {}", entry.catalog.to_string()).into()),
+        },
+        (None, None) => return Err("set productid or name".into()),
+    };
+    if Catalog::id_to_catalog(&product.typeid.to_string()) != entry.catalog {
+        return Err(format!(
+            "{} is listed under {}, not {}",
+            product.name,
+            product.typename,
+            entry.catalog.to_string()
+        )
+        .into());
+    }
+    let downloaddetail = choose_variant(&product, entry.variant.as_deref())?;
+    let scope = match &entry.scope {
+        Some(scope) => scope.clone(),
+        None => get_install_scope(&entry.catalog),
+    };
+    let existing = installmanifest
+        .records
+        .iter()
+        .find(|r| {
+            r.productid == product.id
+                && r.downloadname == downloaddetail.downloadname
+                && r.scope == scope
+        })
+        .filter(|r| {
+            r.directories
+                .iter()
+                .all(|d| Path::new(&r.location).join(d).is_dir())
+        })
+        .cloned();
+
+    // The store only offers the latest upload, so a pin can keep or refuse but never downgrade
+    let pin_mismatch = match (&entry.changed, &entry.md5) {
+        (Some(changed), _) if *changed != product.changed => Some(format!(
+            "pinned to changed {} but the store has {}",
+            changed, product.changed
+        )),
+        (_, Some(md5)) if *md5 != downloaddetail.downloadmd5sum => Some(format!(
+            "pinned to md5 {} but the store has {}",
+            md5, downloaddetail.downloadmd5sum
+        )),
+        _ => None,
+    };
+    let action = match existing {
+        Some(record) if is_current(&record, entry, &product, &downloaddetail) => {
+            SyncAction::Keep(record)
+        }
+        existing => {
+            if let Some(mismatch) = pin_mismatch {
+                return Err(mismatch.into());
+            }
+            match existing {
+                Some(record) => SyncAction::Update(Box::new(product), downloaddetail, record),
+                None => SyncAction::Install(Box::new(product), downloaddetail),
+            }
+        }
+    };
+    Ok(SyncStep {
+        catalog: entry.catalog.clone(),
+        scope,
+        action,
+        apply: entry.apply,
+        directory: entry.directory.clone(),
+    })
+}
+
+// Whether an installed record satisfies the entry without touching it
+fn is_current(
+    record: &InstallRecord,
+    entry: &SyncEntry,
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+) -> bool {
+    if entry.changed.is_some() || entry.md5.is_some() {
+        return entry.changed.as_ref().is_none_or(|c| *c == record.changed)
+            && entry
+                .md5
+                .as_ref()
+                .is_none_or(|m| *m == record.downloadmd5sum);
+    }
+    let same_md5 = record.downloadmd5sum.is_empty()
+        || downloaddetail.downloadmd5sum.is_empty()
+        || record.downloadmd5sum == downloaddetail.downloadmd5sum;
+    record.changed == product.changed && same_md5
+}
+
+fn choose_variant(product: &Product, variant: Option<&str>) -> Result<DownloadDetail> {
+    let names: Vec<&str> = product
+        .downloaddetails
+        .iter()
+        .map(|d| d.downloadname.as_str())
+        .collect();
+    let matching: Vec<&DownloadDetail> = match variant {
+        Some(pattern) => product
+            .downloaddetails
+            .iter()
+            .filter(|d| wildcard_match(pattern, &d.downloadname))
+            .collect(),
+        None => product.downloaddetails.iter().collect(),
+    };
+    match matching.len() {
+        1 => Ok(matching[0].clone()),
+        0 => Err(format!("no file matches, the store has {}", names.join(", ")).into()),
+        _ => Err(format!(
+            "set variant to pick one of {}",
+            matching
+                .iter()
+                .map(|d| d.downloadname.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )
+        .into()),
+    }
+}
+
+// Shell style matching with * and ?, on the whole name
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn apply_directory(step: &SyncStep, record: &InstallRecord) -> String {
+    match &step.directory {
+        Some(directory) if record.directories.contains(directory) => directory.clone(),
+        _ => record.directories.first().cloned().unwrap_or_default(),
+    }
+}
+
+// Runs the plan in order, one message per step that did something
+pub fn execute_sync(plan: &SyncPlan) -> Vec<std::result::Result<String, String>> {
+    let mut results = vec![];
+    for step in plan.steps.iter().filter(|s| s.is_change()) {
+        let record = match &step.action {
+            SyncAction::Install(product, downloaddetail)
+            | SyncAction::Update(product, downloaddetail, _) => {
+                match install_theme(
+                    product,
+                    downloaddetail,
+                    &step.catalog,
+                    &step.scope,
+                    &ConflictResolution::Replace,
+                ) {
+                    Ok(outcome) => {
+                        results.push(Ok(format!("Installed {}", outcome.record.name)));
+                        outcome.record
+                    }
+                    Err(e) => {
+                        results.push(Err(format!("Failed to install {} : {}", product.name, e)));
+                        continue;
+                    }
+                }
+            }
+            SyncAction::Keep(record) => record.clone(),
+            SyncAction::Remove(record) => {
+                results.push(
+                    uninstall_theme(record)
+                        .map(|_| format!("Removed {}", record.name))
+                        .map_err(|e| format!("Failed to remove {} : {}", record.name, e)),
+                );
+                continue;
+            }
+        };
+        if step.apply {
+            let directory = apply_directory(step, &record);
+            results.push(
+                apply_theme(&step.catalog, &directory)
+                    .map(|_| format!("Applied {}", directory))
+                    .map_err(|e| format!("Failed to apply {} : {}", directory, e)),
+            );
+        }
+    }
+    // Applied keys must reach dconf before the process exits
+    gio::Settings::sync();
+    results
+}
+
+// `linuxthemestore sync <manifest.json> [--dry-run] [--yes]`
+pub fn run_cli(args: &[String]) -> glib::ExitCode {
+    let mut path = None;
+    let mut dry_run = false;
+    let mut assume_yes = false;
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" | "-n" => dry_run = true,
+            "--yes" | "-y" => assume_yes = true,
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg.clone()),
+            _ => {
+                eprintln!("Unknown argument {}", arg);
+                path = None;
+                break;
+            }
+        }
+    }
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: linuxthemestore sync <manifest.json> [--dry-run] [--yes]");
+            return glib::ExitCode::FAILURE;
+        }
+    };
+    let syncmanifest = match SyncManifest::load(Path::new(&path)) {
+        Ok(syncmanifest) => syncmanifest,
+        Err(e) => {
+            eprintln!("Failed to read {} : {}", path, e);
+            return glib::ExitCode::FAILURE;
+        }
+    };
+
+    let plan = plan_sync(&syncmanifest);
+    println!("Plan for {}:", path);
+    for step in &plan.steps {
+        println!("  {}", step.describe());
+    }
+    if !plan.errors.is_empty() {
+        for error in &plan.errors {
+            eprintln!("error: {}", error);
+        }
+        return glib::ExitCode::FAILURE;
+    }
+    if !plan.has_changes() {
+        println!("Nothing to do");
+        return glib::ExitCode::SUCCESS;
+    }
+    if dry_run {
+        return glib::ExitCode::SUCCESS;
+    }
+    if !assume_yes {
+        print!("Proceed? [y/N] ");
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        let _ = std::io::stdin().read_line(&mut answer);
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Aborted");
+            return glib::ExitCode::FAILURE;
+        }
+    }
+
+    let mut failed = false;
+    for result in execute_sync(&plan) {
+        match result {
+            Ok(message) => println!("{}", message),
+            Err(message) => {
+                failed = true;
+                eprintln!("{}", message);
+            }
+        }
+    }
+    match failed {
+        true => glib::ExitCode::FAILURE,
+        false => glib::ExitCode::SUCCESS,
+    }
+}
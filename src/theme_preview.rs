@@ -0,0 +1,746 @@
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use adw::gdk::prelude::DisplayExt;
+use adw::gio::{self, FileIcon};
+use adw::glib::prelude::Cast;
+use adw::gtk::prelude::{
+    BoxExt, CheckButtonExt, EditableExt, GridExt, GtkWindowExt, RangeExt, ToggleButtonExt,
+    WidgetExt,
+};
+use adw::gtk::{
+    gdk, glib, Align, Box as GtkBox, Button, CheckButton, ContentFit, CssProvider, DropDown, Entry,
+    FlowBox, Grid, HeaderBar, Image, Label, LevelBar, ListBox, Notebook, Orientation, Picture,
+    ProgressBar, Scale, ScrolledWindow, SearchEntry, SelectionMode, SpinButton, Switch,
+    ToggleButton, Window,
+};
+use adw::prelude::AdwDialogExt;
+
+use crate::index_theme::IndexTheme;
+use crate::installer::{download_archive, install_tar};
+use crate::xcursor::{self, XcursorImage};
+use crate::{Catalog, DownloadDetail, Product, Result};
+
+// Extracted themes being tried, one directory per product, removed when the preview closes
+const PREVIEW_DIR: &str = "/tmp/themeinstaller/preview";
+
+// Standard names a complete icon theme is expected to draw, folders, places,
+// mimetypes, then common apps under their freedesktop and desktop file names
+const PREVIEW_ICON_NAMES: [&str; 30] = [
+    "folder",
+    "folder-documents",
+    "folder-download",
+    "folder-music",
+    "folder-pictures",
+    "folder-videos",
+    "user-home",
+    "user-desktop",
+    "user-trash",
+    "drive-harddisk",
+    "network-workgroup",
+    "text-x-generic",
+    "image-x-generic",
+    "audio-x-generic",
+    "video-x-generic",
+    "application-pdf",
+    "application-x-executable",
+    "package-x-generic",
+    "text-x-script",
+    "x-office-document",
+    "firefox",
+    "org.gnome.Terminal",
+    "utilities-terminal",
+    "org.gnome.Nautilus",
+    "system-file-manager",
+    "accessories-text-editor",
+    "preferences-system",
+    "system-software-install",
+    "web-browser",
+    "help-browser",
+];
+const PREVIEW_ICON_SIZES: [i32; 5] = [16, 24, 32, 48, 64];
+// Cursor shapes apps use most, each with the file names themes ship it under
+const PREVIEW_CURSORS: [(&str, &[&str]); 12] = [
+    ("Default", &["default", "left_ptr", "arrow"]),
+    ("Pointer", &["pointer", "hand2", "hand1", "pointing_hand"]),
+    ("Text", &["text", "xterm", "ibeam"]),
+    ("Wait", &["wait", "watch"]),
+    ("Progress", &["progress", "left_ptr_watch", "half-busy"]),
+    ("Help", &["help", "question_arrow", "whats_this"]),
+    ("Crosshair", &["crosshair", "cross", "tcross"]),
+    ("Move", &["move", "fleur", "grabbing", "size_all"]),
+    (
+        "Not Allowed",
+        &["not-allowed", "crossed_circle", "forbidden"],
+    ),
+    (
+        "Resize Horizontal",
+        &[
+            "ew-resize",
+            "sb_h_double_arrow",
+            "h_double_arrow",
+            "size_hor",
+        ],
+    ),
+    (
+        "Resize Vertical",
+        &[
+            "ns-resize",
+            "sb_v_double_arrow",
+            "v_double_arrow",
+            "size_ver",
+        ],
+    ),
+    (
+        "Resize Diagonal",
+        &["nwse-resize", "bd_double_arrow", "size_fdiag"],
+    ),
+];
+// Milliseconds, the fastest an animated cursor is played
+const MIN_FRAME_DELAY: u32 = 10;
+
+// A theme unpacked outside the theme directories for previewing only
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThemePreview {
+    Gtk(GtkThemePreview),
+    Icons(IconThemePreview),
+    Cursors(CursorThemePreview),
+}
+
+// Stylesheets of a GTK theme
+#[derive(Debug, Clone, PartialEq)]
+pub struct GtkThemePreview {
+    pub name: String,
+    dir: PathBuf,
+    css: PathBuf,
+    dark_css: Option<PathBuf>,
+}
+
+// One icon theme of the archive and where its icons are
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconThemePreview {
+    pub name: String,
+    dir: PathBuf,
+    theme_dir: PathBuf,
+    index_theme: IndexTheme,
+}
+
+// Leaves nothing behind, the extracted files are only needed while the preview is open
+fn remove_preview_dir(dir: &Path) {
+    if let Err(e) = fs::remove_dir_all(dir) {
+        eprintln!("Failed to remove {} : {}", dir.display(), e);
+    }
+}
+
+// Parsed cursors of a cursor theme, one entry per shape in PREVIEW_CURSORS
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorThemePreview {
+    pub name: String,
+    dir: PathBuf,
+    cursors: Vec<(String, std::result::Result<Vec<XcursorImage>, String>)>,
+}
+
+impl IconThemePreview {
+    // Closest size match among the theme's own directories, scalable ones match any size
+    // in their range. Icons only inherited from a parent theme are not found.
+    pub fn find_icon(&self, name: &str, size: i32) -> Option<PathBuf> {
+        let mut best: Option<(i32, PathBuf)> = None;
+        for directory in &self.index_theme.directories {
+            let get = |key: &str| self.index_theme.get(directory, key);
+            let parse = |key: &str, fallback: i32| {
+                get(key).and_then(|v| v.parse().ok()).unwrap_or(fallback)
+            };
+            // HiDPI copies of other directories
+            if parse("Scale", 1) > 1 {
+                continue;
+            }
+            let dir_size = parse("Size", 0);
+            let distance = match get("Type").as_deref() {
+                Some("Scalable")
+                    if (parse("MinSize", dir_size)..=parse("MaxSize", dir_size))
+                        .contains(&size) =>
+                {
+                    0
+                }
+                _ => (dir_size - size).abs(),
+            };
+            if best
+                .as_ref()
+                .is_some_and(|(best_distance, _)| *best_distance <= distance)
+            {
+                continue;
+            }
+            for extension in ["svg", "png"] {
+                let path = self
+                    .theme_dir
+                    .join(directory)
+                    .join(format!("{}.{}", name, extension));
+                if path.is_file() {
+                    best = Some((distance, path));
+                    break;
+                }
+            }
+        }
+        best.map(|(_, path)| path)
+    }
+
+    // How many standard icons the theme draws itself at any size, and that in percent
+    pub fn coverage(&self) -> (usize, u32) {
+        let found = PREVIEW_ICON_NAMES
+            .iter()
+            .filter(|name| {
+                PREVIEW_ICON_SIZES
+                    .iter()
+                    .any(|size| self.find_icon(name, *size).is_some())
+            })
+            .count();
+        (found, (found * 100 / PREVIEW_ICON_NAMES.len()) as u32)
+    }
+}
+
+// Unpacks a variant into its own directory below PREVIEW_DIR, returns it and the
+// top level directories of the archive
+fn extract_preview(
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+    catalog: &Catalog,
+) -> Result<(PathBuf, Vec<String>)> {
+    let archive = download_archive(product, downloaddetail, catalog)?;
+    let dir = Path::new(PREVIEW_DIR).join(product.id.to_string());
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    let directories = install_tar(&archive, &dir)?;
+    Ok((dir, directories))
+}
+
+// Blocking, downloads a variant and extracts it for previewing only. Nothing is
+// recorded in the manifest and the installed themes are not touched.
+pub fn prepare_preview(
+    product: &Product,
+    downloaddetail: &DownloadDetail,
+    catalog: &Catalog,
+) -> Result<ThemePreview> {
+    let (dir, directories) = extract_preview(product, downloaddetail, catalog)?;
+    let preview = match catalog {
+        Catalog::Gtk4Themes => directories.iter().find_map(|directory| {
+            let css_dir = dir.join(directory).join("gtk-4.0");
+            let dark_css = css_dir.join("gtk-dark.css");
+            css_dir.join("gtk.css").is_file().then(|| {
+                ThemePreview::Gtk(GtkThemePreview {
+                    name: directory.clone(),
+                    dir: dir.clone(),
+                    css: css_dir.join("gtk.css"),
+                    dark_css: dark_css.is_file().then_some(dark_css),
+                })
+            })
+        }),
+        Catalog::FullIconThemes => directories.iter().find_map(|directory| {
+            let theme_dir = dir.join(directory);
+            let index_theme = IndexTheme::load(&theme_dir).filter(|i| !i.directories.is_empty())?;
+            Some(ThemePreview::Icons(IconThemePreview {
+                name: match index_theme.name.is_empty() {
+                    true => directory.clone(),
+                    false => index_theme.name.clone(),
+                },
+                dir: dir.clone(),
+                theme_dir,
+                index_theme,
+            }))
+        }),
+        Catalog::Cursors => directories.iter().find_map(|directory| {
+            let cursors_dir = dir.join(directory).join("cursors");
+            cursors_dir.is_dir().then(|| {
+                ThemePreview::Cursors(CursorThemePreview {
+                    name: directory.clone(),
+                    dir: dir.clone(),
+                    cursors: load_preview_cursors(&cursors_dir),
+                })
+            })
+        }),
+        _ => None,
+    };
+    match preview {
+        Some(preview) => Ok(preview),
+        None => {
+            let _ = fs::remove_dir_all(&dir);
+            let expected = match catalog {
+                Catalog::Gtk4Themes => "gtk-4.0/gtk.css",
+                Catalog::FullIconThemes => "icon theme index.theme",
+                Catalog::Cursors => "cursors directory",
+                _ => "theme",
+            };
+            Err(format!(
+                "{} has no {} to preview",
+                downloaddetail.downloadname, expected
+            )
+            .into())
+        }
+    }
+}
+
+// First file name of each shape the theme has, parsed
+fn load_preview_cursors(
+    cursors_dir: &Path,
+) -> Vec<(String, std::result::Result<Vec<XcursorImage>, String>)> {
+    PREVIEW_CURSORS
+        .iter()
+        .map(|(label, names)| {
+            let images = match names
+                .iter()
+                .map(|name| cursors_dir.join(name))
+                .find(|path| path.is_file())
+            {
+                Some(path) => xcursor::load(&path).map_err(|e| e.to_string()),
+                None => Err(String::from("Missing")),
+            };
+            (label.to_string(), images)
+        })
+        .collect()
+}
+
+// Opens the preview matching the theme type, `dialog` hosts in-app previews
+pub fn show_preview(preview: ThemePreview, dialog: &adw::PreferencesDialog) -> Result<()> {
+    match preview {
+        ThemePreview::Gtk(gtk_preview) => show_gtk_theme_preview(gtk_preview),
+        ThemePreview::Icons(icon_preview) => {
+            show_icon_theme_preview(icon_preview, dialog);
+            Ok(())
+        }
+        ThemePreview::Cursors(cursor_preview) => {
+            show_cursor_theme_preview(cursor_preview, dialog);
+            Ok(())
+        }
+    }
+}
+
+// Every standard icon at every preview size, icons the theme lacks are marked
+fn show_icon_theme_preview(preview: IconThemePreview, dialog: &adw::PreferencesDialog) {
+    let (found, percent) = preview.coverage();
+    let grid = Grid::builder()
+        .row_spacing(6)
+        .column_spacing(18)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .halign(Align::Center)
+        .build();
+    for (column, size) in PREVIEW_ICON_SIZES.iter().enumerate() {
+        grid.attach(
+            &Label::builder()
+                .label(format!("{}px", size))
+                .css_classes(vec!["dimmed", "caption"])
+                .build(),
+            column as i32 + 1,
+            0,
+            1,
+            1,
+        );
+    }
+    for (row, name) in PREVIEW_ICON_NAMES.iter().enumerate() {
+        let row = row as i32 + 1;
+        grid.attach(
+            &Label::builder().label(*name).xalign(0.0).build(),
+            0,
+            row,
+            1,
+            1,
+        );
+        for (column, size) in PREVIEW_ICON_SIZES.iter().enumerate() {
+            let icon = match preview.find_icon(name, *size) {
+                Some(path) => Image::builder()
+                    .gicon(&FileIcon::new(&gio::File::for_path(path)))
+                    .pixel_size(*size)
+                    .tooltip_text(format!("{} at {}px", name, size))
+                    .build(),
+                None => Image::builder()
+                    .icon_name("image-missing-symbolic")
+                    .pixel_size(16)
+                    .css_classes(vec!["error"])
+                    .tooltip_text(format!("{} is missing", name))
+                    .build(),
+            };
+            grid.attach(&icon, column as i32 + 1, row, 1, 1);
+        }
+    }
+
+    let content = GtkBox::new(Orientation::Vertical, 6);
+    content.append(
+        &Label::builder()
+            .label(format!(
+                "Draws {} of {} standard icons ({}%). Icons inherited from other themes are not counted.",
+                found,
+                PREVIEW_ICON_NAMES.len(),
+                percent
+            ))
+            .wrap(true)
+            .margin_top(12)
+            .margin_start(12)
+            .margin_end(12)
+            .css_classes(vec![match percent {
+                80.. => "success",
+                50.. => "warning",
+                _ => "error",
+            }])
+            .build(),
+    );
+    content.append(
+        &ScrolledWindow::builder()
+            .hscrollbar_policy(adw::gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .child(&grid)
+            .build(),
+    );
+    present_preview_dialog(
+        format!("Icons of {}", preview.name),
+        &content,
+        preview.dir,
+        dialog,
+    );
+}
+
+// Dialog over the detail dialog, removes the extracted files once closed
+fn present_preview_dialog(
+    title: String,
+    content: &GtkBox,
+    dir: PathBuf,
+    dialog: &adw::PreferencesDialog,
+) -> adw::Dialog {
+    let toolbarview = adw::ToolbarView::new();
+    toolbarview.add_top_bar(&adw::HeaderBar::new());
+    toolbarview.set_content(Some(content));
+    let previewdialog = adw::Dialog::builder()
+        .title(title)
+        .content_width(640)
+        .content_height(720)
+        .child(&toolbarview)
+        .build();
+    previewdialog.connect_closed(move |_| remove_preview_dir(&dir));
+    previewdialog.present(Some(dialog));
+    previewdialog
+}
+
+// Common cursor shapes at one nominal size, animated cursors play their frames
+fn show_cursor_theme_preview(preview: CursorThemePreview, dialog: &adw::PreferencesDialog) {
+    let mut sizes: Vec<u32> = preview
+        .cursors
+        .iter()
+        .filter_map(|(_, images)| images.as_ref().ok())
+        .flat_map(|images| xcursor::nominal_sizes(images))
+        .collect();
+    sizes.sort();
+    sizes.dedup();
+
+    let content = GtkBox::new(Orientation::Vertical, 12);
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    let sizelabels: Vec<String> = sizes.iter().map(|size| format!("{}px", size)).collect();
+    let sizebar = GtkBox::new(Orientation::Horizontal, 12);
+    sizebar.append(
+        &Label::builder()
+            .label(match sizes.is_empty() {
+                true => String::from("No readable cursors"),
+                false => format!("Available sizes : {}", sizelabels.join(", ")),
+            })
+            .hexpand(true)
+            .xalign(0.0)
+            .wrap(true)
+            .build(),
+    );
+    let sizelabels: Vec<&str> = sizelabels.iter().map(|label| label.as_str()).collect();
+    let sizedropdown = DropDown::from_strings(&sizelabels);
+    sizedropdown.set_valign(Align::Center);
+    // The size most desktops default to
+    if let Some(index) = (0..sizes.len()).min_by_key(|&index| sizes[index].abs_diff(24)) {
+        sizedropdown.set_selected(index as u32);
+    }
+    sizebar.append(&sizedropdown);
+    content.append(&sizebar);
+
+    let flowbox = FlowBox::builder()
+        .selection_mode(SelectionMode::None)
+        .min_children_per_line(3)
+        .max_children_per_line(4)
+        .row_spacing(12)
+        .column_spacing(12)
+        .build();
+    content.append(&flowbox);
+
+    // Bumped on every redraw and on close, running animations stop when it changes
+    let generation = Rc::new(Cell::new(0u32));
+    let cursors = Rc::new(preview.cursors);
+    let render = {
+        let generation = generation.clone();
+        let flowbox = flowbox.clone();
+        move |size: u32| {
+            generation.set(generation.get() + 1);
+            while let Some(child) = flowbox.first_child() {
+                flowbox.remove(&child);
+            }
+            for (label, images) in cursors.iter() {
+                let cell = GtkBox::new(Orientation::Vertical, 6);
+                cell.add_css_class("card");
+                let frame_size = size.max(48) as i32 + 16;
+                let picture = Picture::builder()
+                    .content_fit(ContentFit::ScaleDown)
+                    .can_shrink(false)
+                    .width_request(frame_size)
+                    .height_request(frame_size)
+                    .margin_top(6)
+                    .build();
+                cell.append(&picture);
+                cell.append(&Label::new(Some(label)));
+                let details = match images {
+                    Ok(images) => {
+                        let frames: Rc<Vec<(gdk::Texture, u32)>> = Rc::new(
+                            xcursor::frames(images, size)
+                                .into_iter()
+                                .map(|image| (cursor_texture(image), image.delay))
+                                .collect(),
+                        );
+                        animate_cursor(&picture, frames.clone(), 0, &generation);
+                        match frames.len() {
+                            1 => String::from("Static"),
+                            count => format!("{} frames", count),
+                        }
+                    }
+                    Err(e) => {
+                        cell.add_css_class("error");
+                        e.clone()
+                    }
+                };
+                cell.append(
+                    &Label::builder()
+                        .label(details)
+                        .css_classes(vec!["caption", "dimmed"])
+                        .wrap(true)
+                        .margin_bottom(6)
+                        .build(),
+                );
+                flowbox.insert(&cell, -1);
+            }
+        }
+    };
+    if let Some(size) = sizes.get(sizedropdown.selected() as usize) {
+        render(*size);
+    }
+    sizedropdown.connect_selected_notify(move |sizedropdown| {
+        if let Some(size) = sizes.get(sizedropdown.selected() as usize) {
+            render(*size);
+        }
+    });
+
+    let previewdialog = present_preview_dialog(
+        format!("Cursors of {}", preview.name),
+        &content,
+        preview.dir,
+        dialog,
+    );
+    previewdialog.connect_closed(move |_| generation.set(generation.get() + 1));
+}
+
+fn cursor_texture(image: &XcursorImage) -> gdk::Texture {
+    gdk::MemoryTexture::new(
+        image.width as i32,
+        image.height as i32,
+        gdk::MemoryFormat::B8g8r8a8Premultiplied,
+        &glib::Bytes::from(&image.pixels),
+        image.width as usize * 4,
+    )
+    .upcast()
+}
+
+// Shows frame `index` and schedules the next one after its delay
+fn animate_cursor(
+    picture: &Picture,
+    frames: Rc<Vec<(gdk::Texture, u32)>>,
+    index: usize,
+    generation: &Rc<Cell<u32>>,
+) {
+    let (texture, delay) = match frames.get(index) {
+        Some(frame) => frame,
+        None => return,
+    };
+    picture.set_paintable(Some(texture));
+    if frames.len() < 2 {
+        return;
+    }
+    let started = generation.get();
+    let picture = picture.clone();
+    let generation = generation.clone();
+    // Some themes store 0, which would spin the main loop
+    let delay = Duration::from_millis((*delay).max(MIN_FRAME_DELAY).into());
+    glib::timeout_add_local_once(delay, move || {
+        if generation.get() == started {
+            let next = (index + 1) % frames.len();
+            animate_cursor(&picture, frames, next, &generation);
+        }
+    });
+}
+
+// Sample widgets styled by the theme. The window lives on its own connection to the
+// display, so the theme's stylesheet never reaches the store's own windows.
+fn show_gtk_theme_preview(preview: GtkThemePreview) -> Result<()> {
+    let display_name = gdk::Display::default()
+        .map(|display| display.name().to_string())
+        .unwrap_or_default();
+    let display = match gdk::Display::open(Some(&display_name)) {
+        Some(display) => display,
+        None => {
+            remove_preview_dir(&preview.dir);
+            return Err(format!("Could not open a preview connection to {}", display_name).into());
+        }
+    };
+    let provider = CssProvider::new();
+    provider.load_from_path(&preview.css);
+    // Same priority GTK gives ~/.config/gtk-4.0/gtk.css, so it looks like an applied theme
+    adw::gtk::style_context_add_provider_for_display(
+        &display,
+        &provider,
+        adw::gtk::STYLE_PROVIDER_PRIORITY_USER,
+    );
+
+    let window = Window::builder()
+        .display(&display)
+        .title(format!("Preview of {}", preview.name))
+        .default_width(720)
+        .default_height(640)
+        .build();
+    let header = HeaderBar::new();
+    header.pack_start(&Button::from_icon_name("list-add-symbolic"));
+    let darktoggle = ToggleButton::builder()
+        .icon_name("weather-clear-night-symbolic")
+        .tooltip_text("Dark Variant")
+        .sensitive(preview.dark_css.is_some())
+        .build();
+    header.pack_end(&darktoggle);
+    window.set_titlebar(Some(&header));
+
+    let dark_provider = provider.clone();
+    let light_css = preview.css.clone();
+    let dark_css = preview.dark_css.clone();
+    darktoggle.connect_toggled(
+        move |darktoggle| match (&dark_css, darktoggle.is_active()) {
+            (Some(dark_css), true) => dark_provider.load_from_path(dark_css),
+            _ => dark_provider.load_from_path(&light_css),
+        },
+    );
+
+    window.set_child(Some(
+        &ScrolledWindow::builder()
+            .hscrollbar_policy(adw::gtk::PolicyType::Never)
+            .child(&build_widget_gallery())
+            .build(),
+    ));
+    window.connect_close_request(move |_| {
+        adw::gtk::style_context_remove_provider_for_display(&display, &provider);
+        remove_preview_dir(&preview.dir);
+        let display = display.clone();
+        // Closed once the window is gone, GTK still needs the connection while closing it
+        adw::glib::idle_add_local_once(move || display.close());
+        adw::glib::Propagation::Proceed
+    });
+    window.present();
+    Ok(())
+}
+
+fn build_widget_gallery() -> GtkBox {
+    let gallery = GtkBox::new(Orientation::Vertical, 18);
+    gallery.set_margin_top(18);
+    gallery.set_margin_bottom(18);
+    gallery.set_margin_start(18);
+    gallery.set_margin_end(18);
+
+    let buttons = GtkBox::new(Orientation::Horizontal, 6);
+    buttons.append(&Button::with_label("Button"));
+    buttons.append(
+        &Button::builder()
+            .label("Suggested")
+            .css_classes(vec!["suggested-action"])
+            .build(),
+    );
+    buttons.append(
+        &Button::builder()
+            .label("Destructive")
+            .css_classes(vec!["destructive-action"])
+            .build(),
+    );
+    buttons.append(
+        &Button::builder()
+            .label("Flat")
+            .css_classes(vec!["flat"])
+            .build(),
+    );
+    buttons.append(&ToggleButton::builder().label("Toggle").active(true).build());
+    buttons.append(&Button::builder().label("Disabled").sensitive(false).build());
+    gallery.append(&buttons);
+
+    let entries = GtkBox::new(Orientation::Horizontal, 6);
+    entries.set_homogeneous(true);
+    let entry = Entry::builder().placeholder_text("Entry").build();
+    entry.set_text("Some text");
+    entries.append(&entry);
+    entries.append(&SearchEntry::new());
+    entries.append(&SpinButton::with_range(0.0, 100.0, 1.0));
+    gallery.append(&entries);
+
+    let toggles = GtkBox::new(Orientation::Horizontal, 12);
+    toggles.append(&Switch::builder().active(true).valign(Align::Center).build());
+    toggles.append(&Switch::builder().valign(Align::Center).build());
+    let check = CheckButton::with_label("Check");
+    check.set_active(true);
+    toggles.append(&check);
+    let radio = CheckButton::with_label("Radio");
+    let other_radio = CheckButton::with_label("Other Radio");
+    other_radio.set_group(Some(&radio));
+    radio.set_active(true);
+    toggles.append(&radio);
+    toggles.append(&other_radio);
+    toggles.append(&DropDown::from_strings(&["Drop Down", "Second", "Third"]));
+    gallery.append(&toggles);
+
+    let scale = Scale::with_range(Orientation::Horizontal, 0.0, 100.0, 1.0);
+    scale.set_value(40.0);
+    gallery.append(&scale);
+    gallery.append(&ProgressBar::builder().fraction(0.6).build());
+    let levelbar = LevelBar::for_interval(0.0, 1.0);
+    levelbar.set_value(0.7);
+    gallery.append(&levelbar);
+
+    let list = ListBox::builder()
+        .selection_mode(SelectionMode::Single)
+        .css_classes(vec!["boxed-list"])
+        .build();
+    for label in ["First Row", "Second Row", "Third Row"] {
+        list.append(
+            &Label::builder()
+                .label(label)
+                .xalign(0.0)
+                .margin_top(12)
+                .margin_bottom(12)
+                .margin_start(12)
+                .build(),
+        );
+    }
+    gallery.append(&list);
+
+    let notebook = Notebook::new();
+    for label in ["Tab", "Another Tab"] {
+        notebook.append_page(
+            &Label::builder()
+                .label(format!("{} content", label))
+                .margin_top(24)
+                .margin_bottom(24)
+                .build(),
+            Some(&Label::new(Some(label))),
+        );
+    }
+    gallery.append(&notebook);
+    gallery
+}
@@ -0,0 +1,162 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crate::index_theme::IndexTheme;
+use crate::Catalog;
+
+// Result of checking one installed theme directory against what its catalog needs
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    pub directory: String,
+    pub issues: Vec<String>,
+    pub gtk3: bool,
+    pub gtk4: bool,
+    pub libadwaita: bool,
+}
+
+impl ValidationReport {
+    // e.g. "GTK3, GTK4, libadwaita", empty for non GTK themes
+    pub fn compatibility(&self) -> String {
+        let mut toolkits = vec![];
+        if self.gtk3 {
+            toolkits.push("GTK3");
+        }
+        if self.gtk4 {
+            toolkits.push("GTK4");
+        }
+        if self.libadwaita {
+            toolkits.push("libadwaita");
+        }
+        toolkits.join(", ")
+    }
+}
+
+pub fn validate_theme(theme_dir: &Path, catalog: &Catalog) -> ValidationReport {
+    let mut report = ValidationReport {
+        directory: theme_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    if !theme_dir.is_dir() {
+        report
+            .issues
+            .push(format!("{} was not extracted", theme_dir.display()));
+        return report;
+    }
+
+    match catalog {
+        Catalog::FullIconThemes => validate_icon_theme(theme_dir, &mut report),
+        Catalog::Cursors => validate_cursor_theme(theme_dir, &mut report),
+        Catalog::Gtk4Themes => validate_gtk_theme(theme_dir, &mut report),
+        Catalog::GnomeShellThemes => {
+            if !theme_dir.join("gnome-shell/gnome-shell.css").exists() {
+                report
+                    .issues
+                    .push("Missing gnome-shell/gnome-shell.css".to_string());
+            }
+        }
+        Catalog::KDEThemes => {
+            if !theme_dir.join("metadata.desktop").exists()
+                && !theme_dir.join("metadata.json").exists()
+            {
+                report
+                    .issues
+                    .push("Missing metadata.desktop or metadata.json".to_string());
+            }
+        }
+    }
+    report
+}
+
+fn validate_icon_theme(theme_dir: &Path, report: &mut ValidationReport) {
+    let index_theme = match IndexTheme::load(theme_dir) {
+        Some(index_theme) => index_theme,
+        None => {
+            report.issues.push("Missing index.theme".to_string());
+            return;
+        }
+    };
+    if index_theme.directories.is_empty() {
+        report
+            .issues
+            .push("index.theme does not list any Directories".to_string());
+    } else if !index_theme
+        .directories
+        .iter()
+        .any(|d| theme_dir.join(d).is_dir())
+    {
+        report
+            .issues
+            .push("None of the Directories in index.theme exist".to_string());
+    }
+}
+
+fn validate_cursor_theme(theme_dir: &Path, report: &mut ValidationReport) {
+    if !theme_dir.join("index.theme").exists() {
+        report.issues.push("Missing index.theme".to_string());
+    }
+    let entries = match fs::read_dir(theme_dir.join("cursors")) {
+        Ok(entries) => entries,
+        Err(_) => {
+            report.issues.push("Missing cursors directory".to_string());
+            return;
+        }
+    };
+    let mut total = 0;
+    let mut not_xcursor = 0;
+    for entry in entries.flatten() {
+        total += 1;
+        if !is_xcursor_file(&entry.path()) {
+            not_xcursor += 1;
+        }
+    }
+    if total == 0 {
+        report
+            .issues
+            .push("The cursors directory is empty".to_string());
+    } else if not_xcursor > 0 {
+        report.issues.push(format!(
+            "{} of {} files in cursors are not X11 cursors",
+            not_xcursor, total
+        ));
+    }
+}
+
+// X11 cursor files start with the "Xcur" magic, symlinked aliases are followed
+fn is_xcursor_file(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    match fs::File::open(path) {
+        Ok(mut file) => file.read_exact(&mut magic).is_ok() && &magic == b"Xcur",
+        Err(_) => false,
+    }
+}
+
+fn validate_gtk_theme(theme_dir: &Path, report: &mut ValidationReport) {
+    report.gtk3 = theme_dir.join("gtk-3.0/gtk.css").exists();
+    report.gtk4 = theme_dir.join("gtk-4.0/gtk.css").exists();
+    // libadwaita apps only pick up themes that define its named colors
+    report.libadwaita = report.gtk4 && css_defines(&theme_dir.join("gtk-4.0"), "window_bg_color");
+
+    if !report.gtk3 && !report.gtk4 {
+        report
+            .issues
+            .push("Missing gtk-3.0/gtk.css and gtk-4.0/gtk.css".to_string());
+    } else if !report.gtk4 {
+        report.issues.push("Missing gtk-4.0/gtk.css".to_string());
+    }
+}
+
+fn css_defines(css_dir: &Path, color_name: &str) -> bool {
+    let needle = format!("@define-color {}", color_name);
+    match fs::read_dir(css_dir) {
+        Ok(entries) => entries.flatten().any(|entry| {
+            let path = entry.path();
+            path.extension().is_some_and(|ext| ext == "css")
+                && fs::read_to_string(&path).is_ok_and(|css| css.contains(&needle))
+        }),
+        Err(_) => false,
+    }
+}
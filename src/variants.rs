@@ -0,0 +1,239 @@
+use std::collections::BTreeSet;
+
+// Longest first so .tar.xz is not cut as .xz
+const ARCHIVE_EXTENSIONS: [&str; 10] = [
+    ".tar.bz2", ".tar.zst", ".tar.xz", ".tar.gz", ".tbz2", ".tgz", ".txz", ".tar", ".zip", ".7z",
+];
+const ACCENT_COLORS: [&str; 24] = [
+    "blue",
+    "red",
+    "green",
+    "purple",
+    "pink",
+    "orange",
+    "yellow",
+    "teal",
+    "grey",
+    "gray",
+    "brown",
+    "cyan",
+    "magenta",
+    "indigo",
+    "lime",
+    "mint",
+    "aqua",
+    "violet",
+    "sky",
+    "nord",
+    "dracula",
+    "gruvbox",
+    "everforest",
+    "catppuccin",
+];
+
+// The ways variants of one product differ, in the order the chips are shown
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dimension {
+    Scheme,
+    Accent,
+    Size,
+    Component,
+}
+
+impl Dimension {
+    pub fn get_all_dimensions() -> [Dimension; 4] {
+        [
+            Dimension::Scheme,
+            Dimension::Accent,
+            Dimension::Size,
+            Dimension::Component,
+        ]
+    }
+    pub fn to_string(&self) -> &str {
+        match self {
+            Dimension::Scheme => "Style",
+            Dimension::Accent => "Color",
+            Dimension::Size => "Size",
+            Dimension::Component => "Part",
+        }
+    }
+}
+
+// What a download file name says about the variant, None where it says nothing
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct VariantTags {
+    pub scheme: Option<String>,
+    pub accent: Option<String>,
+    pub size: Option<String>,
+    pub component: Option<String>,
+}
+
+impl VariantTags {
+    pub fn get(&self, dimension: &Dimension) -> Option<&str> {
+        match dimension {
+            Dimension::Scheme => self.scheme.as_deref(),
+            Dimension::Accent => self.accent.as_deref(),
+            Dimension::Size => self.size.as_deref(),
+            Dimension::Component => self.component.as_deref(),
+        }
+    }
+}
+
+// e.g. "Theme-Dark-Compact-Blue.tar.xz" is Dark, Blue and Compact
+pub fn parse_variant(downloadname: &str) -> VariantTags {
+    let mut tags = VariantTags::default();
+    for token in tokens(strip_archive_extension(downloadname)) {
+        let token = token.as_str();
+        if tags.scheme.is_none()
+            && let Some(scheme) = scheme_tag(token)
+        {
+            tags.scheme = Some(scheme.to_string());
+        } else if tags.accent.is_none() && ACCENT_COLORS.contains(&token) {
+            let accent = match token {
+                "gray" => "grey",
+                _ => token,
+            };
+            tags.accent = Some(title_case(accent));
+        } else if tags.size.is_none()
+            && let Some(size) = size_tag(token)
+        {
+            tags.size = Some(size.to_string());
+        } else if tags.component.is_none()
+            && let Some(component) = component_tag(token)
+        {
+            tags.component = Some(component.to_string());
+        }
+    }
+    tags
+}
+
+fn strip_archive_extension(downloadname: &str) -> &str {
+    let lowercase = downloadname.to_ascii_lowercase();
+    for extension in ARCHIVE_EXTENSIONS {
+        if lowercase.ends_with(extension) {
+            return &downloadname[..downloadname.len() - extension.len()];
+        }
+    }
+    downloadname
+}
+
+// Lowercase words split at separators and camel case humps, "WhiteSur-Dark_blue" is white, sur, dark, blue
+fn tokens(name: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut previous_lowercase = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            previous_lowercase = false;
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && previous_lowercase && !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+        previous_lowercase = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn title_case(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn scheme_tag(token: &str) -> Option<&'static str> {
+    match token {
+        "dark" | "night" => Some("Dark"),
+        "darker" | "darkest" => Some("Darker"),
+        "light" | "day" => Some("Light"),
+        _ => None,
+    }
+}
+
+fn size_tag(token: &str) -> Option<&'static str> {
+    match token {
+        "compact" => Some("Compact"),
+        "small" => Some("Small"),
+        "large" | "big" => Some("Large"),
+        "hdpi" => Some("HiDPI"),
+        "xhdpi" => Some("XHiDPI"),
+        _ => None,
+    }
+}
+
+fn component_tag(token: &str) -> Option<&'static str> {
+    let name = token.trim_end_matches(|c: char| c.is_ascii_digit());
+    match name {
+        "gtk" => Some("GTK"),
+        "shell" => Some("Shell"),
+        "kde" | "plasma" => Some("KDE"),
+        "kvantum" => Some("Kvantum"),
+        "xfwm" => Some("Xfwm"),
+        "cinnamon" => Some("Cinnamon"),
+        "metacity" => Some("Metacity"),
+        _ => None,
+    }
+}
+
+// Value of a dimension for variants whose name does not mention it, e.g. the plain
+// Theme.tar.xz next to Theme-Dark.tar.xz
+pub const DEFAULT_VALUE: &str = "Default";
+
+// Values per dimension that tell the variants apart, a dimension all variants share is left out.
+// Untagged variants get DEFAULT_VALUE, listed last, so they can be filtered for as well.
+pub fn dimension_values(variants: &[VariantTags]) -> Vec<(Dimension, Vec<String>)> {
+    let mut dimensions = vec![];
+    for dimension in Dimension::get_all_dimensions() {
+        let values: BTreeSet<&str> = variants.iter().filter_map(|v| v.get(&dimension)).collect();
+        let untagged = variants.iter().any(|v| v.get(&dimension).is_none());
+        if values.len() > 1 || (values.len() == 1 && untagged) {
+            let mut values: Vec<String> = values.into_iter().map(String::from).collect();
+            if untagged {
+                values.push(String::from(DEFAULT_VALUE));
+            }
+            dimensions.push((dimension, values));
+        }
+    }
+    dimensions
+}
+
+// A variant passes when it has one of the selected values of every dimension something is selected in
+pub fn matches_selection(tags: &VariantTags, selection: &[(Dimension, BTreeSet<String>)]) -> bool {
+    selection.iter().all(|(dimension, values)| {
+        values.is_empty() || values.contains(tags.get(dimension).unwrap_or(DEFAULT_VALUE))
+    })
+}
+
+// Index of the variant closest to the desktop's color scheme with the default color and size.
+// Nothing is recommended when no file name mentions a scheme.
+pub fn recommended_variant(variants: &[VariantTags], prefer_dark: bool) -> Option<usize> {
+    if variants.len() < 2 || variants.iter().all(|v| v.scheme.is_none()) {
+        return None;
+    }
+    let score = |tags: &VariantTags| {
+        let scheme = match (tags.scheme.as_deref(), prefer_dark) {
+            (Some("Dark"), true) | (Some("Light"), false) => 3,
+            (Some("Darker"), true) | (None, false) => 2,
+            (None, true) => 1,
+            _ => 0,
+        };
+        scheme * 4 + tags.accent.is_none() as u32 + tags.size.is_none() as u32
+    };
+    let mut best: Option<(usize, u32)> = None;
+    for (index, tags) in variants.iter().enumerate() {
+        let tags_score = score(tags);
+        if best.is_none_or(|(_, best_score)| tags_score > best_score) {
+            best = Some((index, tags_score));
+        }
+    }
+    best.map(|(index, _)| index)
+}
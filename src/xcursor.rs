@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::Path;
+
+use crate::Result;
+
+// Layout from the Xcursor file format in libXcursor, all integers little endian
+const MAGIC: &[u8; 4] = b"Xcur";
+const IMAGE_CHUNK_TYPE: u32 = 0xfffd0002;
+const IMAGE_HEADER_SIZE: usize = 36;
+// Largest width or height the format allows
+const MAX_IMAGE_SIZE: u32 = 0x7fff;
+
+// One frame of a cursor at one nominal size
+#[derive(Debug, Clone, PartialEq)]
+pub struct XcursorImage {
+    pub nominal_size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    // Milliseconds this frame is shown in an animation
+    pub delay: u32,
+    // Premultiplied ARGB words as stored, which is B8G8R8A8 premultiplied in memory
+    pub pixels: Vec<u8>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(format!("Xcursor data ends at {} bytes", data.len()).into()),
+    }
+}
+
+// All image chunks of a cursor file in table of contents order
+pub fn parse(data: &[u8]) -> Result<Vec<XcursorImage>> {
+    if data.get(0..4) != Some(MAGIC.as_slice()) {
+        return Err("Not an Xcursor file".into());
+    }
+    let header_size = read_u32(data, 4)? as usize;
+    let toc_entries = read_u32(data, 12)? as usize;
+    let mut images = vec![];
+    for entry in 0..toc_entries {
+        let entry_offset = header_size + entry * 12;
+        if read_u32(data, entry_offset)? != IMAGE_CHUNK_TYPE {
+            continue;
+        }
+        let position = read_u32(data, entry_offset + 8)? as usize;
+        images.push(parse_image(data, position)?);
+    }
+    Ok(images)
+}
+
+fn parse_image(data: &[u8], position: usize) -> Result<XcursorImage> {
+    let field = |index: usize| read_u32(data, position + index * 4);
+    if field(1)? != IMAGE_CHUNK_TYPE {
+        return Err(format!("No image chunk at {}", position).into());
+    }
+    let (width, height) = (field(4)?, field(5)?);
+    if width == 0 || height == 0 || width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return Err(format!("Invalid cursor image size {}x{}", width, height).into());
+    }
+    let start = position + IMAGE_HEADER_SIZE;
+    let length = width as usize * height as usize * 4;
+    let pixels = match data.get(start..start + length) {
+        Some(pixels) => pixels.to_vec(),
+        None => return Err(format!("Cursor image at {} is truncated", position).into()),
+    };
+    Ok(XcursorImage {
+        nominal_size: field(2)?,
+        width,
+        height,
+        xhot: field(6)?,
+        yhot: field(7)?,
+        delay: field(8)?,
+        pixels,
+    })
+}
+
+pub fn load(path: &Path) -> Result<Vec<XcursorImage>> {
+    parse(&fs::read(path)?)
+}
+
+// Nominal sizes present in a cursor, smallest first
+pub fn nominal_sizes(images: &[XcursorImage]) -> Vec<u32> {
+    let mut sizes: Vec<u32> = images.iter().map(|image| image.nominal_size).collect();
+    sizes.sort();
+    sizes.dedup();
+    sizes
+}
+
+// Animation frames at the available nominal size closest to `size`
+pub fn frames(images: &[XcursorImage], size: u32) -> Vec<&XcursorImage> {
+    let closest = match nominal_sizes(images)
+        .into_iter()
+        .min_by_key(|nominal_size| nominal_size.abs_diff(size))
+    {
+        Some(closest) => closest,
+        None => return vec![],
+    };
+    images
+        .iter()
+        .filter(|image| image.nominal_size == closest)
+        .collect()
+}